@@ -1,23 +1,395 @@
-use std::{iter::FromIterator, rc::Rc};
+use std::{
+    borrow::Borrow,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fmt::{self, Debug, Display},
+    iter::FromIterator,
+    rc::Rc,
+};
 
+use indexmap::IndexMap;
 use openapi_utils::ReferenceOrExt;
-use openapiv3::{ArrayType, ObjectType, Operation, Parameter, SchemaKind, Type};
+use openapiv3::{
+    AdditionalProperties, ArrayType, Encoding, Example, IntegerFormat, IntegerType, MediaType,
+    NumberFormat, NumberType, ObjectType, Operation, Parameter, ParameterData,
+    ParameterSchemaOrContent, QueryStyle, ReferenceOr, Schema, SchemaKind, StringFormat,
+    StringType, Type, VariantOrUnknownOrEmpty,
+};
 
 use proptest::{
     arbitrary::any,
+    bool::weighted,
     collection::vec,
+    num,
     prelude::{any_with, Arbitrary},
-    strategy::{BoxedStrategy, Just, Strategy, Union},
+    sample::{select, Index},
+    strategy::{BoxedStrategy, Just, Strategy, Union, ValueTree},
+    string::string_regex,
+    test_runner::TestRunner,
 };
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    body::{self, Body, Content, Part},
+    dictionary::{self, Dictionary},
+    files::{self, FileUpload},
+    formats::FormatRegistry,
+    malformed,
+    merge::merge_all_of,
+    mutate::mutate_json,
+    openapi31::Keywords,
+    spec::Resolver,
+    style,
+};
+
+/// Length of generated strings whose schema does not specify `maxLength`
+const DEFAULT_STRING_LENGTH: usize = 32;
+
+/// Maximum number of bytes of generated binary bodies
+const MAX_BINARY_LENGTH: usize = 1024;
+
+/// Maximum number of files uploaded for an array of files
+const MAX_FILES: usize = 3;
+
+/// Maximum number of undeclared properties added to objects that allow them
+const MAX_ADDITIONAL_PROPERTIES: usize = 3;
+
+/// Number of nested properties and items whose constraints are broken in
+/// negative mode
+const MAX_VIOLATION_DEPTH: usize = 3;
+
+/// Options controlling how values are generated from the schemas
+#[derive(Debug, Clone)]
+pub struct GenerationConfig {
+    /// Probability of generating a value that deliberately violates a constraint of its schema
+    pub violation_rate: f64,
+    /// Probability of including an optional property or parameter
+    pub optional_rate: f64,
+    /// Probability of generating `null` for a nullable value
+    pub null_rate: f64,
+    /// Probability of sending a parameter as a string that ignores its schema
+    pub type_violation_rate: f64,
+    /// Probability of starting from an example or default value of the specification
+    pub example_rate: f64,
+    /// Probability of replacing a string with a payload from the attack dictionary
+    pub attack_rate: f64,
+    /// Probability of sending a request body that is not well-formed
+    pub malformed_rate: f64,
+    /// Whether to send read-only properties, which the server should not accept
+    pub inject_read_only: bool,
+    /// Whether uploaded files are unchanged files of the declared type
+    pub strict_files: bool,
+    /// Number of nested references followed before values are kept as small as
+    /// their schema allows
+    pub max_depth: usize,
+    /// Maximum number of items of arrays without `maxItems`
+    pub max_size: usize,
+    /// Generators for the `format` of strings
+    pub formats: FormatRegistry,
+    /// Attack payloads mixed into strings
+    pub dictionary: Dictionary,
+}
+
+impl Default for GenerationConfig {
+    fn default() -> Self {
+        GenerationConfig {
+            violation_rate: 0.1,
+            optional_rate: 0.5,
+            null_rate: 0.1,
+            type_violation_rate: 0.,
+            example_rate: 0.2,
+            attack_rate: 0.05,
+            malformed_rate: 0.05,
+            inject_read_only: false,
+            strict_files: false,
+            max_depth: 4,
+            max_size: 10,
+            formats: FormatRegistry::default(),
+            dictionary: Dictionary::default(),
+        }
+    }
+}
+
+impl GenerationConfig {
+    /// Turns off everything that deliberately breaks the specification, so that
+    /// generated values are valid.
+    pub fn without_violations(self) -> Self {
+        GenerationConfig {
+            violation_rate: 0.,
+            type_violation_rate: 0.,
+            attack_rate: 0.,
+            malformed_rate: 0.,
+            inject_read_only: false,
+            strict_files: true,
+            ..self
+        }
+    }
+}
+
+type StrategyCache = HashMap<(String, usize), BoxedStrategy<serde_json::Value>>;
+
+/// What is needed to generate values of the schemas of a specification
+#[derive(Clone)]
+pub struct GenerationContext {
+    config: Rc<GenerationConfig>,
+    resolver: Rc<Resolver>,
+    /// Strategies of referenced schemas by reference and depth
+    cache: Rc<RefCell<StrategyCache>>,
+    /// Number of references followed to get to the current schema
+    depth: usize,
+}
+
+impl GenerationContext {
+    pub fn new(config: GenerationConfig, resolver: Rc<Resolver>) -> Self {
+        GenerationContext {
+            config: Rc::new(config),
+            resolver,
+            cache: Default::default(),
+            depth: 0,
+        }
+    }
+
+    /// Whether the maximum depth was reached and values should be kept as small
+    /// as their schema allows.
+    fn is_truncated(&self) -> bool {
+        self.depth >= self.config.max_depth
+    }
+
+    fn referenced_schema_to_json(&self, reference: &str) -> BoxedStrategy<serde_json::Value> {
+        let key = (reference.to_owned(), self.depth);
+        let cached = RefCell::borrow(&self.cache).get(&key).cloned();
+        if let Some(strategy) = cached {
+            return strategy;
+        }
+        let strategy = match self.resolver.schema(reference) {
+            Ok(schema) => match self.resolver.keywords(reference) {
+                Some(keywords) => keywords_to_json(schema, keywords, self),
+                None => schema_to_json(schema, self),
+            },
+            // Reported by `ArbitraryParameters::unsupported`
+            Err(_) => any_json_value(),
+        };
+        self.cache.borrow_mut().insert(key, strategy.clone());
+        strategy
+    }
+}
+
+impl From<GenerationConfig> for GenerationContext {
+    fn from(config: GenerationConfig) -> Self {
+        GenerationContext::new(config, Default::default())
+    }
+}
+
 pub struct ArbitraryParameters {
     operation: Operation,
+    context: GenerationContext,
+    /// Part of the request that breaks a constraint in negative mode
+    violated: Option<Violated>,
+}
+
+/// Part of a request replaced with a value breaking one of its constraints. Without
+/// a value the part is left out.
+#[derive(Clone)]
+struct Violated {
+    target: Target,
+    value: Option<BoxedStrategy<serde_json::Value>>,
+}
+
+#[derive(Clone, PartialEq)]
+enum Target {
+    Parameter {
+        location: &'static str,
+        name: String,
+    },
+    Body {
+        media_type: String,
+    },
 }
 
 impl ArbitraryParameters {
-    pub fn new(operation: Operation) -> Self {
-        ArbitraryParameters { operation }
+    pub fn new(operation: Operation, context: GenerationContext) -> Self {
+        ArbitraryParameters {
+            operation,
+            context,
+            violated: None,
+        }
+    }
+
+    /// Generates the value of a parameter, or `None` when it is not sent, with the
+    /// attacks in it. Optional parameters are sent at the optional rate and the one
+    /// that breaks a constraint is always sent, unless it breaks being required.
+    fn parameter(
+        &self,
+        location: &'static str,
+        parameter_data: &ParameterData,
+    ) -> BoxedStrategy<(Option<serde_json::Value>, Attacks)> {
+        let value = match &self.violated {
+            Some(Violated {
+                target: Target::Parameter { location: l, name },
+                value,
+            }) if *l == location && *name == parameter_data.name => match value {
+                Some(value) => value.clone().prop_map(Some).boxed(),
+                None => Just(None).boxed(),
+            },
+            _ if parameter_data.required => generate_parameter(parameter_data, &self.context)
+                .prop_map(Some)
+                .boxed(),
+            _ => (
+                weighted(self.context.config.optional_rate),
+                generate_parameter(parameter_data, &self.context),
+            )
+                .prop_map(|(include, value)| include.then_some(value))
+                .boxed(),
+        };
+        let dictionary = self.context.config.dictionary.clone();
+        value
+            .prop_map(move |mut value| {
+                let attacks = value
+                    .as_mut()
+                    .map(|value| dictionary.take_attacks(value))
+                    .unwrap_or_default();
+                (value, attacks)
+            })
+            .boxed()
+    }
+    /// Finds the parts of the schemas of the operation whose values cannot be
+    /// generated as they specify.
+    pub fn unsupported(&self) -> Vec<Unsupported> {
+        let mut finder = UnsupportedFinder {
+            resolver: &self.context.resolver,
+            visited: HashSet::new(),
+            found: vec![],
+        };
+        for ref_or_param in &self.operation.parameters {
+            let parameter_data = match ref_or_param.to_item_ref() {
+                Parameter::Query { parameter_data, .. }
+                | Parameter::Header { parameter_data, .. }
+                | Parameter::Path { parameter_data, .. }
+                | Parameter::Cookie { parameter_data, .. } => parameter_data,
+            };
+            match &parameter_data.format {
+                ParameterSchemaOrContent::Schema(schema) => finder.visit(schema),
+                ParameterSchemaOrContent::Content(content) => content
+                    .values()
+                    .for_each(|media_type| finder.visit_media_type(media_type)),
+            }
+        }
+        if let Some(ReferenceOr::Item(request_body)) = &self.operation.request_body {
+            for media_type in request_body.content.values() {
+                finder.visit_media_type(media_type);
+            }
+        }
+        finder.found
+    }
+}
+
+/// Part of a schema whose values cannot be generated as it specifies
+#[derive(Debug, PartialEq)]
+pub enum Unsupported {
+    /// Pattern using syntax the regex generator does not support, for which
+    /// strings of the right length are generated instead
+    Pattern(String),
+    /// `allOf` whose schemas cannot be merged, with the reason
+    AllOf(String),
+    /// Reference to a `false` schema of OpenAPI 3.1, which no value satisfies
+    False(String),
+    /// Reference that cannot be resolved, with the reason, for which values of
+    /// any type are generated instead
+    Reference(String),
+}
+
+impl Unsupported {
+    /// Whether values ignoring it are still worth sending when requests are not
+    /// required to be valid
+    pub fn is_ignorable(&self) -> bool {
+        matches!(
+            self,
+            Unsupported::Pattern(_) | Unsupported::False(_) | Unsupported::Reference(_)
+        )
+    }
+}
+
+impl Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Unsupported::Pattern(pattern) => write!(f, "unsupported pattern `{pattern}`"),
+            Unsupported::AllOf(reason) => write!(f, "unable to merge allOf schemas: {reason}"),
+            Unsupported::False(reference) => write!(f, "schema `{reference}` allows no value"),
+            Unsupported::Reference(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+struct UnsupportedFinder<'a> {
+    resolver: &'a Resolver,
+    /// References already visited, as schemas may be recursive
+    visited: HashSet<String>,
+    found: Vec<Unsupported>,
+}
+
+impl UnsupportedFinder<'_> {
+    fn visit_media_type(&mut self, media_type: &MediaType) {
+        if let Some(schema) = &media_type.schema {
+            self.visit(schema);
+        }
+    }
+
+    fn visit<T: Borrow<Schema>>(&mut self, ref_or_schema: &ReferenceOr<T>) {
+        let schema = match ref_or_schema {
+            ReferenceOr::Item(schema) => schema.borrow(),
+            ReferenceOr::Reference { reference } => {
+                if !self.visited.insert(reference.clone()) {
+                    return;
+                }
+                if let Some(keywords) = self.resolver.keywords(reference) {
+                    if keywords.is_false {
+                        self.found.push(Unsupported::False(reference.clone()));
+                    }
+                    keywords
+                        .prefix_items
+                        .iter()
+                        .for_each(|schema| self.visit(schema));
+                }
+                match self.resolver.schema(reference) {
+                    Ok(schema) => schema,
+                    Err(e) => {
+                        self.found.push(Unsupported::Reference(format!("{e:#}")));
+                        return;
+                    }
+                }
+            }
+        };
+        match &schema.schema_kind {
+            SchemaKind::Type(Type::String(string)) => {
+                if let Some(pattern) = &string.pattern {
+                    if compile_pattern(pattern, None).is_none() {
+                        self.found.push(Unsupported::Pattern(pattern.clone()));
+                    }
+                }
+            }
+            SchemaKind::Type(Type::Object(object)) => {
+                object
+                    .properties
+                    .values()
+                    .for_each(|schema| self.visit(schema));
+                if let Some(AdditionalProperties::Schema(schema)) = &object.additional_properties {
+                    self.visit(schema);
+                }
+            }
+            SchemaKind::Type(Type::Array(array)) => self.visit(&array.items),
+            SchemaKind::AllOf { all_of: schemas } => {
+                if let Err(e) = merge_all_of(schemas, self.resolver) {
+                    self.found.push(Unsupported::AllOf(format!("{e:#}")));
+                }
+                schemas.iter().for_each(|schema| self.visit(schema))
+            }
+            SchemaKind::AnyOf { any_of: schemas } | SchemaKind::OneOf { one_of: schemas } => {
+                schemas.iter().for_each(|schema| self.visit(schema))
+            }
+            _ => {}
+        }
     }
 }
 
@@ -27,149 +399,1671 @@ impl Default for ArbitraryParameters {
     }
 }
 
-fn generate_json_object(object: &ObjectType) -> BoxedStrategy<serde_json::Value> {
-    let mut vec = Vec::with_capacity(object.properties.len());
+/// Picks a value from `invalid` with probability `rate` and from `valid` otherwise.
+fn with_violations<T: Debug + 'static>(
+    valid: BoxedStrategy<T>,
+    invalid: Vec<BoxedStrategy<T>>,
+    rate: f64,
+) -> BoxedStrategy<T> {
+    const PRECISION: u32 = 1000;
+    let invalid_weight = (rate.clamp(0., 1.) * PRECISION as f64).round() as u32;
+    match invalid_weight {
+        _ if invalid.is_empty() => valid,
+        0 => valid,
+        PRECISION => Union::new(invalid).boxed(),
+        _ => Union::new_weighted(vec![
+            (PRECISION - invalid_weight, valid),
+            (invalid_weight, Union::new(invalid).boxed()),
+        ])
+        .boxed(),
+    }
+}
+
+/// Values that each break the named constraint of a schema
+type Violations<T> = Vec<(String, BoxedStrategy<T>)>;
+
+/// Classes of the attack payloads in a part of the request
+type Attacks = Vec<String>;
+
+/// Mixes values breaking one of the constraints into the valid ones at the
+/// violation rate.
+fn with_named_violations<T: Debug + 'static>(
+    (valid, invalid): (BoxedStrategy<T>, Violations<T>),
+    rate: f64,
+) -> BoxedStrategy<T> {
+    let invalid = invalid.into_iter().map(|(_, strategy)| strategy).collect();
+    with_violations(valid, invalid, rate)
+}
+
+/// Collects the values of `example` and `examples` of a parameter or media type.
+fn examples(
+    example: &Option<serde_json::Value>,
+    examples: &IndexMap<String, ReferenceOr<Example>>,
+) -> Vec<serde_json::Value> {
+    example
+        .iter()
+        .cloned()
+        .chain(examples.values().filter_map(|example| match example {
+            ReferenceOr::Item(example) => example.value.clone(),
+            ReferenceOr::Reference { .. } => None,
+        }))
+        .collect()
+}
+
+/// Starts from one of the `seeds` from the specification at the example rate,
+/// sending it unchanged or, at the violation rate, mutated. Other values come
+/// from `generated`.
+fn with_examples(
+    generated: BoxedStrategy<serde_json::Value>,
+    seeds: Vec<serde_json::Value>,
+    config: &GenerationConfig,
+) -> BoxedStrategy<serde_json::Value> {
+    if seeds.is_empty() {
+        return generated;
+    }
+    let mutated = seeds.iter().cloned().map(mutate_json).collect();
+    let seeded = with_violations(select(seeds).boxed(), mutated, config.violation_rate);
+    with_violations(generated, vec![seeded], config.example_rate)
+}
+
+/// Translates an ECMA 262 regex used by OpenAPI to the syntax understood by the
+/// regex generator. Anchors are dropped as the generated string is the whole match
+/// and ECMA's ASCII-only classes are spelled out.
+fn pattern_to_regex(pattern: &str) -> String {
+    let pattern = pattern.strip_prefix('^').unwrap_or(pattern);
+    let pattern = match pattern.strip_suffix('$') {
+        Some(stripped) if !stripped.ends_with('\\') || stripped.ends_with("\\\\") => stripped,
+        _ => pattern,
+    };
+
+    let mut regex = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            regex.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('d') => regex.push_str("[0-9]"),
+            Some('D') => regex.push_str("[^0-9]"),
+            Some('w') => regex.push_str("[A-Za-z0-9_]"),
+            Some('W') => regex.push_str("[^A-Za-z0-9_]"),
+            Some(escaped) => {
+                regex.push('\\');
+                regex.push(escaped);
+            }
+            None => regex.push('\\'),
+        }
+    }
+    regex
+}
+
+/// Compiles a pattern to a generator of matching strings and a matcher. With a
+/// maximum length, unbounded repetitions are bounded by it, as the generator
+/// repeats at most 32 times otherwise. Returns `None` for patterns using syntax
+/// that is not supported, e.g. lookarounds.
+pub fn compile_pattern(
+    pattern: &str,
+    max: Option<usize>,
+) -> Option<(BoxedStrategy<String>, Regex)> {
+    let regex = pattern_to_regex(pattern);
+    let generated = match max {
+        Some(max) => bound_repetitions(&regex, max),
+        None => regex.clone(),
+    };
+    let strategy = string_regex(&generated).ok()?.boxed();
+    let matcher = Regex::new(&format!("^(?:{regex})$")).ok()?;
+    Some((strategy, matcher))
+}
+
+/// Replaces the `*`, `+` and `{n,}` quantifiers of a regex with ones repeating
+/// at most `max` times.
+fn bound_repetitions(regex: &str, max: usize) -> String {
+    let mut bounded = String::with_capacity(regex.len());
+    let mut chars = regex.chars().peekable();
+    let mut in_class = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                bounded.push(c);
+                bounded.extend(chars.next());
+            }
+            '[' => {
+                in_class = true;
+                bounded.push(c);
+            }
+            ']' => {
+                in_class = false;
+                bounded.push(c);
+            }
+            '*' if !in_class => bounded.push_str(&format!("{{0,{max}}}")),
+            '+' if !in_class => bounded.push_str(&format!("{{1,{max}}}")),
+            '{' if !in_class => {
+                let quantifier: String = chars.clone().take_while(|c| *c != '}').collect();
+                match quantifier.strip_suffix(',').map(str::parse::<usize>) {
+                    Some(Ok(n)) => {
+                        bounded.push_str(&format!("{{{n},{}}}", max.max(n)));
+                        chars.nth(quantifier.chars().count());
+                    }
+                    _ => bounded.push(c),
+                }
+            }
+            _ => bounded.push(c),
+        }
+    }
+    bounded
+}
+
+/// Whether some of the first values of a strategy are accepted, so that filtering
+/// them does not reject everything.
+fn can_generate<T: Debug>(strategy: &BoxedStrategy<T>, accept: impl Fn(&T) -> bool) -> bool {
+    let mut runner = TestRunner::deterministic();
+    (0..100).any(|_| {
+        strategy
+            .new_tree(&mut runner)
+            .is_ok_and(|tree| accept(&tree.current()))
+    })
+}
+
+/// Generates strings that do not match a pattern, both near misses of the valid
+/// strings and arbitrary ones, leaving out those that can hardly be generated.
+pub fn pattern_violations(
+    valid: &BoxedStrategy<String>,
+    matcher: Regex,
+) -> Vec<BoxedStrategy<String>> {
+    let mismatching = move |s: &String| !matcher.is_match(s);
+    vec![near_miss(valid.clone()), any::<String>().boxed()]
+        .into_iter()
+        .filter(|candidates| can_generate(candidates, mismatching.clone()))
+        .map(|candidates| {
+            candidates
+                .prop_filter("string must not match the pattern", mismatching.clone())
+                .boxed()
+        })
+        .collect()
+}
+
+/// Inserts a random character into the generated strings to get values that are
+/// close to, but do not match, a pattern.
+fn near_miss(valid: BoxedStrategy<String>) -> BoxedStrategy<String> {
+    (valid, any::<char>(), any::<Index>())
+        .prop_map(|(s, c, index)| {
+            let mut chars: Vec<_> = s.chars().collect();
+            chars.insert(index.index(chars.len() + 1), c);
+            String::from_iter(chars)
+        })
+        .boxed()
+}
+
+fn string_of_length(min: usize, max: usize) -> BoxedStrategy<String> {
+    vec(any::<char>(), min..=max)
+        .prop_map(String::from_iter)
+        .boxed()
+}
+
+/// Generates members of a string enum and, as violations, values that resemble
+/// them but are not members.
+fn string_enum_strategies(enumeration: &[String]) -> (BoxedStrategy<String>, Violations<String>) {
+    let mut near_members: Vec<_> = enumeration
+        .iter()
+        .flat_map(|member| {
+            let mut chars = member.chars();
+            chars.next_back();
+            [
+                member.to_uppercase(),
+                member.to_lowercase(),
+                member.chars().rev().collect(),
+                chars.as_str().to_owned(),
+                format!(" {member} "),
+                format!("{member}_unknown"),
+            ]
+        })
+        .filter(|value| !enumeration.contains(value))
+        .collect();
+    near_members.sort();
+    near_members.dedup();
+
+    let members = enumeration.to_vec();
+    let mut invalid = vec![(
+        "enum".to_owned(),
+        any::<String>()
+            .prop_filter("value must not be an enum member", move |value| {
+                !members.contains(value)
+            })
+            .boxed(),
+    )];
+    if !near_members.is_empty() {
+        invalid.push(("enum".to_owned(), select(near_members).boxed()));
+    }
+
+    (select(enumeration.to_vec()).boxed(), invalid)
+}
+
+fn generate_string(string: &StringType, config: &GenerationConfig) -> BoxedStrategy<String> {
+    let string = with_named_violations(string_strategies(string, config), config.violation_rate);
+    match config.dictionary.strategy() {
+        Some(attacks) => {
+            let attacks = attacks
+                .prop_map(|(class, payload)| dictionary::marked(&class, &payload))
+                .boxed();
+            with_violations(string, vec![attacks], config.attack_rate)
+        }
+        None => string,
+    }
+}
+
+fn string_strategies(
+    string: &StringType,
+    config: &GenerationConfig,
+) -> (BoxedStrategy<String>, Violations<String>) {
+    if !string.enumeration.is_empty() {
+        return string_enum_strategies(&string.enumeration);
+    }
+    if string.pattern.is_none() {
+        if let Some(format) = config.formats.get(&string.format) {
+            let constraint = match serde_json::to_value(&string.format) {
+                Ok(serde_json::Value::String(name)) => format!("format `{name}`"),
+                _ => "format".to_owned(),
+            };
+            let invalid = format
+                .invalid
+                .iter()
+                .map(|invalid| (constraint.clone(), invalid.clone()))
+                .collect();
+            return (format.valid.clone(), invalid);
+        }
+    }
+
+    let min = string.min_length.unwrap_or(0);
+    let max = string
+        .max_length
+        .unwrap_or(min + DEFAULT_STRING_LENGTH)
+        .max(min);
+    let has_length = string.min_length.is_some() || string.max_length.is_some();
+
+    let pattern = string
+        .pattern
+        .as_deref()
+        .and_then(|pattern| compile_pattern(pattern, has_length.then_some(max)));
+    let in_range = move |s: &String| (min..=max).contains(&s.chars().count());
+
+    let valid = match &pattern {
+        // Patterns that cannot satisfy the length, e.g. `^[a-z]{3}$` with a
+        // minLength of 10, would reject every generated value
+        Some((pattern, _)) if has_length && can_generate(pattern, in_range) => pattern
+            .clone()
+            .prop_filter("string must satisfy minLength and maxLength", in_range)
+            .boxed(),
+        Some((pattern, _)) if !has_length => pattern.clone(),
+        _ => string_of_length(min, max),
+    };
+
+    let mut invalid = vec![];
+    if min > 0 {
+        invalid.push((format!("minLength {min}"), string_of_length(0, min - 1)));
+    }
+    if string.max_length.is_some() {
+        invalid.push((
+            format!("maxLength {max}"),
+            string_of_length(max + 1, max + DEFAULT_STRING_LENGTH),
+        ));
+    }
+    if let (Some(pattern), Some((_, matcher))) = (&string.pattern, pattern) {
+        let constraint = format!("pattern `{pattern}`");
+        for mismatching in pattern_violations(&valid, matcher) {
+            invalid.push((constraint.clone(), mismatching));
+        }
+    }
+
+    (valid, invalid)
+}
+
+/// Picks one of the given values, or nothing if there are none.
+fn one_of_values(values: Vec<serde_json::Value>) -> Option<BoxedStrategy<serde_json::Value>> {
+    (!values.is_empty()).then(|| Union::new(values.into_iter().map(Just)).boxed())
+}
+
+/// Mixes values on the edges of the allowed range, where off-by-one and overflow
+/// bugs hide, with values from the whole range.
+fn biased_to_boundaries(
+    boundaries: Vec<serde_json::Value>,
+    uniform: BoxedStrategy<serde_json::Value>,
+) -> BoxedStrategy<serde_json::Value> {
+    match one_of_values(boundaries) {
+        Some(boundaries) => Union::new_weighted(vec![(1, boundaries), (2, uniform)]).boxed(),
+        None => uniform,
+    }
+}
+
+fn integer_to_json(n: i128) -> Option<serde_json::Value> {
+    i64::try_from(n)
+        .map(Into::into)
+        .or_else(|_| u64::try_from(n).map(Into::into))
+        .ok()
+}
+
+fn generate_integer(
+    integer: &IntegerType,
+    config: &GenerationConfig,
+) -> BoxedStrategy<serde_json::Value> {
+    with_named_violations(integer_strategies(integer), config.violation_rate)
+}
+
+fn integer_strategies(
+    integer: &IntegerType,
+) -> (
+    BoxedStrategy<serde_json::Value>,
+    Violations<serde_json::Value>,
+) {
+    if !integer.enumeration.is_empty() {
+        let members = integer.enumeration.clone();
+        let (min, max) = (
+            *members.iter().min().expect("enum is not empty"),
+            *members.iter().max().expect("enum is not empty"),
+        );
+        let near_members: Vec<_> = [min as i128 - 1, max as i128 + 1, 0, -1]
+            .iter()
+            .filter(|n| !members.iter().any(|member| *member as i128 == **n))
+            .filter_map(|n| integer_to_json(*n))
+            .collect();
+        let mut invalid = vec![(
+            "enum".to_owned(),
+            any::<i64>()
+                .prop_filter("value must not be an enum member", move |n| {
+                    !members.contains(n)
+                })
+                .prop_map_into()
+                .boxed(),
+        )];
+        invalid.extend(one_of_values(near_members).map(|values| ("enum".to_owned(), values)));
+        return (
+            select(integer.enumeration.clone()).prop_map_into().boxed(),
+            invalid,
+        );
+    }
+
+    let (type_min, type_max) = match integer.format {
+        VariantOrUnknownOrEmpty::Item(IntegerFormat::Int32) => (i32::MIN as i128, i32::MAX as i128),
+        _ => (i64::MIN as i128, i64::MAX as i128),
+    };
+    let lower = integer
+        .minimum
+        .map_or(type_min, |min| {
+            min as i128 + integer.exclusive_minimum as i128
+        })
+        .max(type_min);
+    let upper = integer
+        .maximum
+        .map_or(type_max, |max| {
+            max as i128 - integer.exclusive_maximum as i128
+        })
+        .min(type_max);
+    let step = integer.multiple_of.filter(|step| *step > 0).unwrap_or(1) as i128;
+    // Valid values are `k * step` for `k` in `first..=last`
+    let (first, last) = (
+        lower.div_euclid(step) + (lower.rem_euclid(step) != 0) as i128,
+        upper.div_euclid(step),
+    );
+
+    let valid = if first <= last {
+        let boundaries = [first, first + 1, last - 1, last]
+            .iter()
+            .map(|k| k * step)
+            .chain([0, 1, -1])
+            .filter(|n| (first * step..=last * step).contains(n) && n % step == 0)
+            .filter_map(integer_to_json)
+            .collect();
+        let uniform = (first..=last)
+            .prop_filter_map("integer must fit into JSON number", move |k| {
+                integer_to_json(k * step)
+            })
+            .boxed();
+        biased_to_boundaries(boundaries, uniform)
+    } else {
+        // Constraints cannot be satisfied, so ignore them
+        (type_min..=type_max)
+            .prop_filter_map("integer must fit into JSON number", integer_to_json)
+            .boxed()
+    };
+
+    let mut invalid_values = vec![];
+    if let Some(min) = integer.minimum {
+        invalid_values.push((bound("minimum", min, integer.exclusive_minimum), lower - 1));
+    }
+    if let Some(max) = integer.maximum {
+        invalid_values.push((bound("maximum", max, integer.exclusive_maximum), upper + 1));
+    }
+    if integer.format == VariantOrUnknownOrEmpty::Item(IntegerFormat::Int32) {
+        invalid_values.extend([
+            ("format `int32`".to_owned(), i32::MAX as i128 + 1),
+            ("format `int32`".to_owned(), i32::MIN as i128 - 1),
+        ]);
+    }
+    let mut invalid: Vec<_> = invalid_values
+        .into_iter()
+        .filter_map(|(constraint, n)| Some((constraint, Just(integer_to_json(n)?).boxed())))
+        .collect();
+    // The value after a multiple stays below the next one, which is in range
+    if step > 1 && first < last {
+        invalid.push((
+            format!("multipleOf {step}"),
+            (first..last)
+                .prop_filter_map("integer must fit into JSON number", move |k| {
+                    integer_to_json(k * step + 1)
+                })
+                .boxed(),
+        ));
+    }
+
+    (valid, invalid)
+}
+
+/// Names a `minimum` or `maximum` constraint, e.g. `exclusiveMinimum 0`.
+fn bound(name: &str, value: impl std::fmt::Display, exclusive: bool) -> String {
+    if exclusive {
+        let mut chars = name.chars();
+        let first = chars.next().map(|c| c.to_ascii_uppercase());
+        format!(
+            "exclusive{}{} {value}",
+            first.unwrap_or_default(),
+            chars.as_str()
+        )
+    } else {
+        format!("{name} {value}")
+    }
+}
+
+fn generate_number(
+    number: &NumberType,
+    config: &GenerationConfig,
+) -> BoxedStrategy<serde_json::Value> {
+    with_named_violations(number_strategies(number), config.violation_rate)
+}
+
+fn number_strategies(
+    number: &NumberType,
+) -> (
+    BoxedStrategy<serde_json::Value>,
+    Violations<serde_json::Value>,
+) {
+    let members: Vec<_> = number
+        .enumeration
+        .iter()
+        .copied()
+        .filter(|n| n.is_finite())
+        .collect();
+    if !members.is_empty() {
+        let (min, max) = members.iter().fold((f64::MAX, f64::MIN), |(min, max), n| {
+            (min.min(*n), max.max(*n))
+        });
+        let near_members = [min - 1., max + 1., members[0] + 0.5, 0.]
+            .iter()
+            .filter(|n| n.is_finite() && !members.contains(n))
+            .map(|n| (*n).into())
+            .collect();
+        return (
+            select(members).prop_map_into().boxed(),
+            one_of_values(near_members)
+                .map(|values| ("enum".to_owned(), values))
+                .into_iter()
+                .collect(),
+        );
+    }
+
+    let is_float = number.format == VariantOrUnknownOrEmpty::Item(NumberFormat::Float);
+    let type_max = if is_float { f32::MAX as f64 } else { f64::MAX };
+    let (mut exclusive_min, mut exclusive_max) =
+        (number.exclusive_minimum, number.exclusive_maximum);
+    let mut lower = number.minimum.unwrap_or(-type_max).max(-type_max);
+    let mut upper = number.maximum.unwrap_or(type_max).min(type_max);
+    if lower > upper || (lower == upper && (exclusive_min || exclusive_max)) {
+        // Constraints cannot be satisfied, so ignore them
+        (lower, upper, exclusive_min, exclusive_max) = (-type_max, type_max, false, false);
+    }
+    let in_range = move |n: &f64| {
+        (lower..=upper).contains(n)
+            && !(exclusive_min && *n == lower)
+            && !(exclusive_max && *n == upper)
+    };
+
+    let mut boundaries = vec![0., 1., -1., lower, upper];
+    if exclusive_min {
+        boundaries.push(lower.next_up());
+    }
+    if exclusive_max {
+        boundaries.push(upper.next_down());
+    }
+    if is_float {
+        boundaries.extend([f32::MIN_POSITIVE as f64, f32::EPSILON as f64]);
+    }
+
+    // Keep the multipliers in the range where doubles represent integers exactly
+    const MAX_EXACT: f64 = (1u64 << 53) as f64;
+    let multiples = number
+        .multiple_of
+        .filter(|step| *step > 0.)
+        .and_then(|step| {
+            let first = (lower / step).ceil().max(-MAX_EXACT) as i64;
+            let last = (upper / step).floor().min(MAX_EXACT) as i64;
+            (first <= last).then_some((step, first, last))
+        });
+
+    let valid = match multiples {
+        Some((step, first, last)) => {
+            boundaries = [first, first + 1, last - 1, last]
+                .iter()
+                .map(|k| *k as f64 * step)
+                .chain([0.])
+                .collect();
+            (first..=last)
+                .prop_map(move |k| k as f64 * step)
+                .prop_filter("number must be in range", in_range)
+                .boxed()
+        }
+        None if (upper - lower).is_finite() => (lower..=upper)
+            .prop_filter("number must be in range", in_range)
+            .boxed(),
+        None if is_float => (num::f32::NORMAL | num::f32::ZERO)
+            .prop_map(|n| n as f64)
+            .prop_filter("number must be in range", in_range)
+            .boxed(),
+        None => (num::f64::NORMAL | num::f64::ZERO)
+            .prop_filter("number must be in range", in_range)
+            .boxed(),
+    };
+    let valid = biased_to_boundaries(
+        boundaries
+            .into_iter()
+            .filter(in_range)
+            .map(Into::into)
+            .collect(),
+        valid.prop_map_into().boxed(),
+    );
+
+    let mut invalid_values = vec![];
+    if let Some(min) = number.minimum {
+        let constraint = bound("minimum", min, exclusive_min);
+        let closest = if exclusive_min { min } else { min.next_down() };
+        invalid_values.extend([(constraint.clone(), min - 1.), (constraint, closest)]);
+    }
+    if let Some(max) = number.maximum {
+        let constraint = bound("maximum", max, exclusive_max);
+        let closest = if exclusive_max { max } else { max.next_up() };
+        invalid_values.extend([(constraint.clone(), max + 1.), (constraint, closest)]);
+    }
+    if is_float {
+        invalid_values.extend([
+            ("format `float`".to_owned(), f32::MAX as f64 * 2.),
+            ("format `float`".to_owned(), f32::MIN as f64 * 2.),
+        ]);
+    }
+    let mut invalid: Vec<_> = invalid_values
+        .into_iter()
+        .filter(|(_, n)| n.is_finite())
+        .map(|(constraint, n)| (constraint, Just(n.into()).boxed()))
+        .collect();
+    if let Some((step, first, last)) = multiples {
+        // Halfway between two multiples, of which the outer ones may be out of range
+        let halfway = (first - 1..=last)
+            .prop_map(move |k| (k as f64 + 0.5) * step)
+            .boxed();
+        if can_generate(&halfway, in_range) {
+            invalid.push((
+                format!("multipleOf {step}"),
+                halfway
+                    .prop_filter("number must be in range", in_range)
+                    .prop_map_into()
+                    .boxed(),
+            ));
+        }
+    }
+
+    (valid, invalid)
+}
+
+/// Generates a value of any JSON type for schemas that do not restrict it
+fn any_json_value() -> BoxedStrategy<serde_json::Value> {
+    Union::new(vec![
+        Just(serde_json::Value::Null).boxed(),
+        any::<bool>().prop_map_into().boxed(),
+        any::<i64>().prop_map_into().boxed(),
+        (num::f64::NORMAL | num::f64::ZERO).prop_map_into().boxed(),
+        any::<String>().prop_map_into().boxed(),
+    ])
+    .boxed()
+}
+
+fn generate_json_object(
+    object: &ObjectType,
+    ctx: &GenerationContext,
+) -> BoxedStrategy<serde_json::Value> {
+    with_named_violations(object_strategies(object, ctx), ctx.config.violation_rate)
+}
+
+fn object_strategies(
+    object: &ObjectType,
+    ctx: &GenerationContext,
+) -> (
+    BoxedStrategy<serde_json::Value>,
+    Violations<serde_json::Value>,
+) {
+    let config = &ctx.config;
+    let mut required = vec![];
+    let mut optional = vec![];
+    let mut required_names = vec![];
     for (name, schema) in &object.properties {
-        let schema_kind = &schema.to_item_ref().schema_kind;
-        vec.push((Just(name.clone()), schema_kind_to_json(schema_kind)));
+        let read_only = ctx
+            .resolver
+            .resolve(schema)
+            .is_ok_and(|schema| schema.schema_data.read_only);
+        let is_required = object.required.contains(name) && !read_only;
+        // Read-only properties are ignored in requests, unless we are checking
+        // whether the server accepts them. Write-only ones are sent as any other.
+        // Past the maximum depth only required properties are generated to end
+        // recursion as soon as possible.
+        if (read_only && !config.inject_read_only) || (ctx.is_truncated() && !is_required) {
+            continue;
+        }
+        let value = ref_or_schema_to_json(schema, ctx);
+        if is_required {
+            required.push((Just(name.clone()), value));
+            required_names.push(name.clone());
+        } else {
+            optional.push((Just(name.clone()), weighted(config.optional_rate), value));
+        }
+    }
+
+    let declared: Vec<_> = object.properties.keys().cloned().collect();
+    let additional_name = string_regex("[a-zA-Z_][a-zA-Z0-9_]{0,15}")
+        .expect("valid regex")
+        .prop_filter("additional property must not be declared", move |name| {
+            !declared.contains(name)
+        })
+        .boxed();
+    let max_additional = if ctx.is_truncated() {
+        0
+    } else {
+        MAX_ADDITIONAL_PROPERTIES
+    };
+    let additional = match &object.additional_properties {
+        Some(AdditionalProperties::Any(false)) => Just(vec![]).boxed(),
+        Some(AdditionalProperties::Schema(schema)) => vec(
+            (additional_name.clone(), ref_or_schema_to_json(schema, ctx)),
+            0..=max_additional.max(object.min_properties.unwrap_or(0)),
+        )
+        .boxed(),
+        Some(AdditionalProperties::Any(true)) | None => vec(
+            (additional_name.clone(), any_json_value()),
+            0..=max_additional.max(object.min_properties.unwrap_or(0)),
+        )
+        .boxed(),
+    };
+
+    let (min_properties, max_properties) = (
+        object.min_properties.unwrap_or(0),
+        object.max_properties.unwrap_or(usize::MAX),
+    );
+    let valid = (required, optional, additional, Just(required_names.clone()))
+        .prop_map(move |(required, optional, additional, required_names)| {
+            let mut object = serde_json::Map::from_iter(required);
+            let mut excluded = vec![];
+            for (name, include, value) in optional {
+                if include {
+                    object.insert(name, value);
+                } else {
+                    excluded.push((name, value));
+                }
+            }
+            object.extend(additional);
+            // Add omitted optional properties to reach minProperties and drop
+            // the ones that are not required to fit into maxProperties
+            let missing = min_properties.saturating_sub(object.len());
+            object.extend(excluded.into_iter().take(missing));
+            let removable: Vec<_> = object
+                .keys()
+                .filter(|name| !required_names.contains(name))
+                .cloned()
+                .collect();
+            let excess = object.len().saturating_sub(max_properties);
+            for name in removable.iter().take(excess) {
+                object.remove(name);
+            }
+            serde_json::Value::Object(object)
+        })
+        .boxed();
+
+    let mut invalid = vec![];
+    for name in required_names {
+        invalid.push((
+            format!("required property `{name}`"),
+            valid
+                .clone()
+                .prop_map(move |mut object| {
+                    if let Some(object) = object.as_object_mut() {
+                        object.remove(&name);
+                    }
+                    object
+                })
+                .boxed(),
+        ));
+    }
+    if object.additional_properties == Some(AdditionalProperties::Any(false)) {
+        invalid.push((
+            "additionalProperties false".to_owned(),
+            (valid.clone(), additional_name, any_json_value())
+                .prop_map(|(mut object, name, value)| {
+                    if let Some(object) = object.as_object_mut() {
+                        object.insert(name, value);
+                    }
+                    object
+                })
+                .boxed(),
+        ));
+    }
+
+    (valid, invalid)
+}
+
+fn generate_json_array(
+    array: &ArrayType,
+    ctx: &GenerationContext,
+) -> BoxedStrategy<serde_json::Value> {
+    let (min, max) = if ctx.is_truncated() {
+        let min = array.min_items.unwrap_or(0);
+        (min, min)
+    } else {
+        let min = array.min_items.unwrap_or(1);
+        (min, array.max_items.unwrap_or(ctx.config.max_size).max(min))
+    };
+    vec(ref_or_schema_to_json(&array.items, ctx), min..=max)
+        .prop_map(serde_json::Value::Array)
+        .boxed()
+}
+
+fn schema_type_to_json(
+    schema_type: &Type,
+    ctx: &GenerationContext,
+) -> BoxedStrategy<serde_json::Value> {
+    let config = &ctx.config;
+    match schema_type {
+        Type::Boolean {} => any::<bool>().prop_map_into::<serde_json::Value>().boxed(),
+        Type::Integer(integer_type) => generate_integer(integer_type, config),
+        Type::Number(number_type) => generate_number(number_type, config),
+        Type::String(string_type) => generate_string(string_type, config)
+            .prop_map_into::<serde_json::Value>()
+            .boxed(),
+        Type::Object(object_type) => generate_json_object(object_type, ctx),
+        Type::Array(array_type) => generate_json_array(array_type, ctx),
+    }
+}
+
+fn schema_kind_to_json(
+    schema_kind: &SchemaKind,
+    ctx: &GenerationContext,
+) -> BoxedStrategy<serde_json::Value> {
+    match schema_kind {
+        SchemaKind::Any(_any) => any::<String>().prop_map_into::<serde_json::Value>().boxed(),
+        SchemaKind::Type(schema_type) => schema_type_to_json(schema_type, ctx).boxed(),
+        SchemaKind::AllOf { all_of: schemas } => match merge_all_of(schemas, &ctx.resolver) {
+            Ok(schema) => schema_to_json(&schema, ctx),
+            // Operations with such schemas are skipped, see `ArbitraryParameters::unsupported`
+            Err(_) => any_json_value(),
+        },
+        SchemaKind::AnyOf { any_of: schemas } | SchemaKind::OneOf { one_of: schemas } => {
+            // Past the maximum depth prefer the alternatives that do not recurse
+            let inline: Vec<_> = schemas
+                .iter()
+                .filter(|ref_or_schema| matches!(ref_or_schema, ReferenceOr::Item(_)))
+                .collect();
+            let alternatives = if ctx.is_truncated() && !inline.is_empty() {
+                inline
+            } else {
+                schemas.iter().collect()
+            };
+            Union::new(
+                alternatives
+                    .into_iter()
+                    .map(|ref_or_schema| ref_or_schema_to_json(ref_or_schema, ctx)),
+            )
+            .boxed()
+        }
+    }
+}
+
+fn schema_to_json(schema: &Schema, ctx: &GenerationContext) -> BoxedStrategy<serde_json::Value> {
+    with_schema_data(
+        schema,
+        schema_kind_to_json(&schema.schema_kind, ctx),
+        &[],
+        ctx,
+    )
+}
+
+/// Generates values of a schema of an OpenAPI 3.1 specification with keywords
+/// `openapiv3` does not model, e.g. the `const` value or the items of
+/// `prefixItems` followed by those of `items`.
+fn keywords_to_json(
+    schema: &Schema,
+    keywords: &Keywords,
+    ctx: &GenerationContext,
+) -> BoxedStrategy<serde_json::Value> {
+    let generated = match (&keywords.constant, &schema.schema_kind) {
+        (Some(constant), _) => with_violations(
+            Just(constant.clone()).boxed(),
+            vec![mutate_json(constant.clone())],
+            ctx.config.violation_rate,
+        ),
+        (None, SchemaKind::Type(Type::Array(array))) if !keywords.prefix_items.is_empty() => {
+            generate_tuple(array, &keywords.prefix_items, ctx)
+        }
+        _ => schema_kind_to_json(&schema.schema_kind, ctx),
+    };
+    with_schema_data(schema, generated, &keywords.examples, ctx)
+}
+
+/// Generates the items of `prefix_items` followed by items of the array.
+fn generate_tuple(
+    array: &ArrayType,
+    prefix_items: &[ReferenceOr<Schema>],
+    ctx: &GenerationContext,
+) -> BoxedStrategy<serde_json::Value> {
+    let prefix: Vec<_> = prefix_items
+        .iter()
+        .map(|ref_or_schema| ref_or_schema_to_json(ref_or_schema, ctx))
+        .collect();
+    let rest = ArrayType {
+        min_items: Some(
+            array
+                .min_items
+                .unwrap_or(0)
+                .saturating_sub(prefix_items.len()),
+        ),
+        max_items: array
+            .max_items
+            .map(|max| max.saturating_sub(prefix_items.len())),
+        ..array.clone()
+    };
+    (prefix, generate_json_array(&rest, ctx))
+        .prop_map(|(mut items, rest)| {
+            if let serde_json::Value::Array(rest) = rest {
+                items.extend(rest);
+            }
+            serde_json::Value::Array(items)
+        })
+        .boxed()
+}
+
+/// Mixes the example, default and `examples` of a schema into the generated
+/// values and `null` if the schema is nullable.
+fn with_schema_data(
+    schema: &Schema,
+    generated: BoxedStrategy<serde_json::Value>,
+    examples: &[serde_json::Value],
+    ctx: &GenerationContext,
+) -> BoxedStrategy<serde_json::Value> {
+    let mut seeds: Vec<_> = schema
+        .schema_data
+        .example
+        .iter()
+        .chain(&schema.schema_data.default)
+        .cloned()
+        .collect();
+    for example in examples {
+        if !seeds.contains(example) {
+            seeds.push(example.clone());
+        }
+    }
+    let value = with_examples(generated, seeds, &ctx.config);
+    if schema.schema_data.nullable {
+        with_violations(
+            value,
+            vec![Just(serde_json::Value::Null).boxed()],
+            ctx.config.null_rate,
+        )
+    } else {
+        value
+    }
+}
+
+/// Generates values of an inline schema or, lazily, of a referenced one, so that
+/// recursive schemas are only expanded as deep as the generated values go.
+fn ref_or_schema_to_json<T: Borrow<Schema>>(
+    ref_or_schema: &ReferenceOr<T>,
+    ctx: &GenerationContext,
+) -> BoxedStrategy<serde_json::Value> {
+    match ref_or_schema {
+        ReferenceOr::Item(schema) => schema_to_json(schema.borrow(), ctx),
+        // Schemas that cannot end their recursion, e.g. with a required property
+        // referencing its own schema, get `null` eventually
+        ReferenceOr::Reference { .. } if ctx.depth >= 2 * ctx.config.max_depth => {
+            Just(serde_json::Value::Null).boxed()
+        }
+        ReferenceOr::Reference { reference } => {
+            let ctx = GenerationContext {
+                depth: ctx.depth + 1,
+                ..ctx.clone()
+            };
+            let reference = reference.clone();
+            Just(())
+                .prop_flat_map(move |()| ctx.referenced_schema_to_json(&reference))
+                .boxed()
+        }
+    }
+}
+
+/// Generates values that break exactly one constraint of a schema. `textual`
+/// values are serialized to strings, e.g. in parameters, so only constraints that
+/// are still broken once serialized are considered.
+fn schema_violations<T: Borrow<Schema>>(
+    ref_or_schema: &ReferenceOr<T>,
+    ctx: &GenerationContext,
+    textual: bool,
+    depth: usize,
+) -> Violations<serde_json::Value> {
+    let schema = match ctx.resolver.resolve(ref_or_schema) {
+        Ok(schema) => schema,
+        Err(_) => return vec![],
+    };
+    let schema_type = match &schema.schema_kind {
+        SchemaKind::Type(schema_type) => schema_type,
+        SchemaKind::AllOf { all_of } => {
+            return merge_all_of(all_of, &ctx.resolver)
+                .map(|merged| schema_violations(&ReferenceOr::Item(merged), ctx, textual, depth))
+                .unwrap_or_default();
+        }
+        // A value breaking one alternative may still match another
+        SchemaKind::AnyOf { .. } | SchemaKind::OneOf { .. } | SchemaKind::Any(_) => return vec![],
+    };
+
+    let (type_name, mut wrong_types) = match (schema_type, textual) {
+        (Type::String(_), false) => ("string", vec![12345.into(), true.into()]),
+        (Type::String(_), true) => ("string", vec![]),
+        (Type::Number(_), _) => ("number", vec!["abc".into(), true.into()]),
+        (Type::Integer(_), _) => ("integer", vec!["abc".into(), 1.5.into()]),
+        (Type::Boolean {}, _) => ("boolean", vec!["abc".into()]),
+        (Type::Object(_), false) => ("object", vec!["abc".into(), serde_json::json!([])]),
+        (Type::Array(_), false) => ("array", vec!["abc".into(), serde_json::json!({})]),
+        // Single values are valid objects and arrays once serialized
+        (Type::Object(_), true) => ("object", vec![]),
+        (Type::Array(_), true) => ("array", vec![]),
+    };
+    if !schema.schema_data.nullable && !textual {
+        wrong_types.push(serde_json::Value::Null);
+    }
+    let mut violations: Violations<_> = one_of_values(wrong_types)
+        .map(|values| (format!("type `{type_name}`"), values))
+        .into_iter()
+        .collect();
+
+    match schema_type {
+        Type::String(string_type) => violations.extend(
+            string_strategies(string_type, &ctx.config)
+                .1
+                .into_iter()
+                .map(|(constraint, value)| (constraint, value.prop_map_into().boxed())),
+        ),
+        Type::Integer(integer_type) => violations.extend(integer_strategies(integer_type).1),
+        Type::Number(number_type) => violations.extend(number_strategies(number_type).1),
+        Type::Boolean {} => {}
+        Type::Object(object_type) => {
+            violations.extend(object_violations(object_type, ctx, textual, depth))
+        }
+        Type::Array(array_type) => {
+            violations.extend(array_violations(array_type, ctx, textual, depth))
+        }
+    }
+    violations
+}
+
+fn object_violations(
+    object: &ObjectType,
+    ctx: &GenerationContext,
+    textual: bool,
+    depth: usize,
+) -> Violations<serde_json::Value> {
+    let (valid, mut violations) = object_strategies(object, ctx);
+    if depth >= MAX_VIOLATION_DEPTH {
+        return violations;
+    }
+    for (name, schema) in &object.properties {
+        let read_only = ctx
+            .resolver
+            .resolve(schema)
+            .is_ok_and(|schema| schema.schema_data.read_only);
+        if read_only && !ctx.config.inject_read_only {
+            continue;
+        }
+        for (constraint, value) in schema_violations(schema, ctx, textual, depth + 1) {
+            let name = name.clone();
+            violations.push((
+                format!("property `{name}`: {constraint}"),
+                (valid.clone(), value)
+                    .prop_map(move |(mut object, value)| {
+                        if let Some(object) = object.as_object_mut() {
+                            object.insert(name.clone(), value);
+                        }
+                        object
+                    })
+                    .boxed(),
+            ));
+        }
+    }
+    violations
+}
+
+fn array_violations(
+    array: &ArrayType,
+    ctx: &GenerationContext,
+    textual: bool,
+    depth: usize,
+) -> Violations<serde_json::Value> {
+    let valid = generate_json_array(array, ctx);
+    let item = ref_or_schema_to_json(&array.items, ctx);
+    let mut violations = vec![];
+    if let Some(min) = array.min_items.filter(|min| *min > 0) {
+        violations.push((
+            format!("minItems {min}"),
+            vec(item.clone(), 0..min)
+                .prop_map(serde_json::Value::Array)
+                .boxed(),
+        ));
+    }
+    if let Some(max) = array.max_items {
+        violations.push((
+            format!("maxItems {max}"),
+            vec(item.clone(), max + 1..=max + ctx.config.max_size)
+                .prop_map(serde_json::Value::Array)
+                .boxed(),
+        ));
+    }
+    if array.unique_items {
+        violations.push((
+            "uniqueItems true".to_owned(),
+            (valid.clone(), item.clone())
+                .prop_map(|(mut array, item)| {
+                    if let Some(items) = array.as_array_mut() {
+                        match items.first().cloned() {
+                            Some(first) if items.len() > 1 => items[1] = first,
+                            Some(first) => items.push(first),
+                            None => items.extend([item.clone(), item]),
+                        }
+                    }
+                    array
+                })
+                .boxed(),
+        ));
+    }
+    if depth >= MAX_VIOLATION_DEPTH {
+        return violations;
+    }
+    for (constraint, value) in schema_violations(&array.items, ctx, textual, depth + 1) {
+        violations.push((
+            format!("items: {constraint}"),
+            (valid.clone(), value, any::<Index>())
+                .prop_map(|(mut array, value, index)| {
+                    if let Some(items) = array.as_array_mut() {
+                        if items.is_empty() {
+                            items.push(value);
+                        } else {
+                            let index = index.index(items.len());
+                            items[index] = value;
+                        }
+                    }
+                    array
+                })
+                .boxed(),
+        ));
+    }
+    violations
+}
+
+fn is_binary(schema: &Schema) -> bool {
+    matches!(
+        &schema.schema_kind,
+        SchemaKind::Type(Type::String(StringType {
+            format: VariantOrUnknownOrEmpty::Item(StringFormat::Binary),
+            ..
+        }))
+    )
+}
+
+/// Returns the properties of an object schema that are files, i.e. binary
+/// strings or arrays of them, which are sent as a part per file, and whether
+/// they are arrays.
+fn file_properties(schema: &ReferenceOr<Schema>, ctx: &GenerationContext) -> Vec<(String, bool)> {
+    let object = match ctx
+        .resolver
+        .resolve(schema)
+        .map(|schema| &schema.schema_kind)
+    {
+        Ok(SchemaKind::Type(Type::Object(object))) => object,
+        _ => return vec![],
+    };
+    object
+        .properties
+        .iter()
+        .filter_map(
+            |(name, property)| match &ctx.resolver.resolve(property).ok()?.schema_kind {
+                SchemaKind::Type(Type::Array(array)) => ctx
+                    .resolver
+                    .resolve(&array.items)
+                    .is_ok_and(is_binary)
+                    .then(|| (name.clone(), true)),
+                _ => ctx
+                    .resolver
+                    .resolve(property)
+                    .is_ok_and(is_binary)
+                    .then(|| (name.clone(), false)),
+            },
+        )
+        .collect()
+}
+
+fn primitive_to_bytes(value: serde_json::Value) -> Vec<u8> {
+    match value {
+        serde_json::Value::Null => vec![],
+        serde_json::Value::String(s) => s.into_bytes(),
+        value => value.to_string().into_bytes(),
+    }
+}
+
+/// Returns the content type of a property of a multipart or form body declared
+/// in its encoding, if it is not a wildcard.
+fn declared_content_type(encoding: &IndexMap<String, Encoding>, name: &str) -> Option<String> {
+    encoding
+        .get(name)
+        .and_then(|encoding| encoding.content_type.as_ref())
+        .and_then(|content_type| content_type.split(',').next())
+        .map(|content_type| content_type.trim().to_owned())
+        .filter(|content_type| !content_type.contains('*'))
+}
+
+/// Splits an object into the parts of a multipart body, with the content types
+/// defaulting to the ones of the OpenAPI specification. File properties are
+/// sent as the generated `uploads`.
+fn to_parts(
+    value: serde_json::Value,
+    mut uploads: Vec<(String, Vec<FileUpload>)>,
+    encoding: &IndexMap<String, Encoding>,
+) -> Vec<Part> {
+    let object = match value {
+        serde_json::Value::Object(object) => object,
+        _ => return vec![],
+    };
+    let mut parts = vec![];
+    for (name, value) in object {
+        let content_type = declared_content_type(encoding, &name);
+        if let Some(index) = uploads.iter().position(|(file, _)| *file == name) {
+            let (_, files) = uploads.swap_remove(index);
+            for file in files {
+                parts.push(Part {
+                    name: name.clone(),
+                    content_type: file.content_type,
+                    filename: Some(file.filename),
+                    content: file.content,
+                });
+            }
+        } else {
+            let (default_content_type, content) = match value {
+                value @ (serde_json::Value::Array(_) | serde_json::Value::Object(_)) => {
+                    ("application/json", value.to_string().into_bytes())
+                }
+                value => ("text/plain", primitive_to_bytes(value)),
+            };
+            parts.push(Part {
+                name,
+                content_type: content_type.unwrap_or_else(|| default_content_type.to_owned()),
+                filename: None,
+                content,
+            });
+        }
+    }
+    parts
+}
+
+/// Serializes the properties of an object as the fields of a form, following
+/// the style of their encoding.
+fn to_form(
+    value: serde_json::Value,
+    encoding: &IndexMap<String, Encoding>,
+) -> Vec<(String, String)> {
+    let object = match value {
+        serde_json::Value::Object(object) => object,
+        _ => return vec![],
+    };
+    object
+        .iter()
+        .flat_map(|(name, value)| {
+            match encoding
+                .get(name)
+                .and_then(|encoding| Some((encoding.style.as_ref()?, encoding.explode)))
+            {
+                Some((style, explode)) => style::query_parameter(name, value, style, explode),
+                None => style::query_parameter(name, value, &QueryStyle::Form, true),
+            }
+        })
+        .collect()
+}
+
+/// Returns the media type without parameters such as `charset`.
+fn essence(media_type_name: &str) -> String {
+    media_type_name
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
+/// Generates a body of the media type from its schema in the encoding given by
+/// the media type.
+fn generate_body(
+    media_type_name: &str,
+    media_type: &MediaType,
+    ctx: &GenerationContext,
+) -> BoxedStrategy<(Body, Attacks)> {
+    let value = match &media_type.schema {
+        Some(schema) => ref_or_schema_to_json(schema, ctx),
+        None => any_json_value(),
+    };
+    let value = with_examples(
+        value,
+        examples(&media_type.example, &media_type.examples),
+        &ctx.config,
+    );
+    encode_body(media_type_name, media_type, value, ctx)
+}
+
+/// Encodes the generated values as bodies of the media type, with the attacks in
+/// them.
+fn encode_body(
+    media_type_name: &str,
+    media_type: &MediaType,
+    value: BoxedStrategy<serde_json::Value>,
+    ctx: &GenerationContext,
+) -> BoxedStrategy<(Body, Attacks)> {
+    let dictionary = ctx.config.dictionary.clone();
+    let value = value.prop_map(move |mut value| {
+        let attacks = dictionary.take_attacks(&mut value);
+        (value, attacks)
+    });
+    let essence = essence(media_type_name);
+    let content = if essence.contains("json") {
+        value
+            .prop_map(|(value, attacks)| (Content::Json(value), attacks))
+            .boxed()
+    } else if essence == "application/x-www-form-urlencoded" {
+        let encoding = media_type.encoding.clone();
+        value
+            .prop_map(move |(value, attacks)| {
+                (Content::FormUrlencoded(to_form(value, &encoding)), attacks)
+            })
+            .boxed()
+    } else if essence.starts_with("multipart/") {
+        let encoding = media_type.encoding.clone();
+        let uploads: Vec<_> = media_type
+            .schema
+            .as_ref()
+            .map(|schema| file_properties(schema, ctx))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, is_array)| {
+                let upload = files::file_upload(
+                    declared_content_type(&encoding, &name),
+                    ctx.config.strict_files,
+                );
+                let count = if is_array { 1..=MAX_FILES } else { 1..=1 };
+                (Just(name), vec(upload, count))
+            })
+            .collect();
+        (value, uploads)
+            .prop_map(move |((value, attacks), uploads)| {
+                let parts = to_parts(value, uploads, &encoding);
+                (Content::Multipart(parts), attacks)
+            })
+            .boxed()
+    } else if essence.contains("xml") {
+        // The root element is named after the referenced schema
+        let root = match &media_type.schema {
+            Some(ReferenceOr::Reference { reference }) => {
+                reference.rsplit('/').next().unwrap_or_default().to_owned()
+            }
+            _ => "root".to_owned(),
+        };
+        value
+            .prop_map(move |(value, attacks)| {
+                (Content::Xml(body::json_to_xml(&root, &value)), attacks)
+            })
+            .boxed()
+    } else if essence.starts_with("text/") {
+        value
+            .prop_map(|(value, attacks)| match value {
+                serde_json::Value::String(s) => (Content::Text(s), attacks),
+                value => (Content::Text(value.to_string()), attacks),
+            })
+            .boxed()
+    } else {
+        files::file_content(MAX_BINARY_LENGTH)
+            .prop_map(|content| (Content::Binary(content), vec![]))
+            .boxed()
+    };
+
+    let content_type = if media_type_name.contains('*') {
+        "application/octet-stream".to_owned()
+    } else {
+        media_type_name.to_owned()
+    };
+    content
+        .prop_map(move |(content, attacks)| {
+            let body = Body {
+                content_type: content_type.clone(),
+                content,
+                malformed: None,
+            };
+            (body, attacks)
+        })
+        .boxed()
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct OptionalBody(Option<Body>);
+
+/// Generates the request body, if the operation has one, with the attacks in it.
+/// Malformed bodies have no attacks, as they are sent for the malformation.
+fn generate_optional_body(args: &ArbitraryParameters) -> BoxedStrategy<(OptionalBody, Attacks)> {
+    let media_types = match args.operation.request_body.as_ref() {
+        Some(ref_or_body) => &ref_or_body.to_item_ref().content,
+        None => return Just((OptionalBody(None), vec![])).boxed(),
+    };
+    if media_types.is_empty() {
+        return Just((OptionalBody(None), vec![])).boxed();
+    }
+    if let Some(Violated {
+        target: Target::Body { media_type },
+        value,
+    }) = &args.violated
+    {
+        return match (value, media_types.get(media_type)) {
+            (Some(value), Some(media_type_item)) => {
+                encode_body(media_type, media_type_item, value.clone(), &args.context)
+                    .prop_map(|(body, attacks)| (OptionalBody(Some(body)), attacks))
+                    .boxed()
+            }
+            _ => Just((OptionalBody(None), vec![])).boxed(),
+        };
     }
-    vec.prop_map(|vec| serde_json::Value::Object(serde_json::Map::from_iter(vec)))
+    let bodies = Union::new(media_types.iter().map(|(media_type_name, media_type)| {
+        generate_body(media_type_name, media_type, &args.context)
+    }))
+    .boxed();
+    let malformed = bodies
+        .clone()
+        .prop_flat_map(|(body, _)| malformed::malformed(body).prop_map(|body| (body, vec![])))
+        .boxed();
+    with_violations(bodies, vec![malformed], args.context.config.malformed_rate)
+        .prop_map(|(body, attacks)| (OptionalBody(Some(body)), attacks))
         .boxed()
 }
 
-fn generate_json_array(array: &ArrayType) -> BoxedStrategy<serde_json::Value> {
-    let items = array.items.to_item_ref();
-    let (min, max) = (array.min_items.unwrap_or(1), array.max_items.unwrap_or(10));
-    vec(schema_kind_to_json(&items.schema_kind), (min, max))
-        .prop_map(serde_json::Value::Array)
-        .boxed()
+/// Generates the value of a parameter from its schema or, serialized, from the
+/// schema of its media type. Strings ignoring the schema are sent at the type
+/// violation rate.
+fn generate_parameter(
+    parameter_data: &ParameterData,
+    ctx: &GenerationContext,
+) -> BoxedStrategy<serde_json::Value> {
+    let value = match &parameter_data.format {
+        ParameterSchemaOrContent::Schema(schema) => with_examples(
+            ref_or_schema_to_json(schema, ctx),
+            examples(&parameter_data.example, &parameter_data.examples),
+            &ctx.config,
+        ),
+        ParameterSchemaOrContent::Content(content) => match content.iter().next() {
+            Some((
+                media_type_name,
+                media_type @ MediaType {
+                    schema: Some(schema),
+                    ..
+                },
+            )) => {
+                let is_json = media_type_name.contains("json");
+                let mut seeds = examples(&parameter_data.example, &parameter_data.examples);
+                seeds.extend(examples(&media_type.example, &media_type.examples));
+                with_examples(ref_or_schema_to_json(schema, ctx), seeds, &ctx.config)
+                    .prop_map(move |value| match value {
+                        serde_json::Value::String(s) if !is_json => s.into(),
+                        value => value.to_string().into(),
+                    })
+                    .boxed()
+            }
+            _ => any::<String>().prop_map_into().boxed(),
+        },
+    };
+    with_violations(
+        value,
+        vec![any::<String>().prop_map_into().boxed()],
+        ctx.config.type_violation_rate,
+    )
 }
 
-fn schema_type_to_json(schema_type: &Type) -> BoxedStrategy<serde_json::Value> {
-    match schema_type {
-        Type::Boolean {} => any::<bool>().prop_map_into::<serde_json::Value>().boxed(),
-        Type::Integer(_integer_type) => any::<i64>().prop_map_into::<serde_json::Value>().boxed(),
-        Type::Number(_number_type) => any::<f32>().prop_map_into::<serde_json::Value>().boxed(),
-        Type::String(_string_type) => any::<String>().prop_map_into::<serde_json::Value>().boxed(),
-        Type::Object(object_type) => generate_json_object(object_type),
-        Type::Array(array_type) => generate_json_array(array_type),
+/// Generates values of a parameter that break one of the constraints of its schema.
+fn parameter_violations(
+    parameter_data: &ParameterData,
+    ctx: &GenerationContext,
+) -> Violations<serde_json::Value> {
+    match &parameter_data.format {
+        ParameterSchemaOrContent::Schema(schema) => schema_violations(schema, ctx, true, 0),
+        ParameterSchemaOrContent::Content(content) => match content.iter().next() {
+            Some((
+                media_type_name,
+                MediaType {
+                    schema: Some(schema),
+                    ..
+                },
+            )) if media_type_name.contains("json") => schema_violations(schema, ctx, false, 0)
+                .into_iter()
+                .map(|(constraint, value)| {
+                    let value = value.prop_map(|value| value.to_string().into()).boxed();
+                    (constraint, value)
+                })
+                .collect(),
+            _ => vec![],
+        },
     }
 }
 
-fn schema_kind_to_json(schema_kind: &SchemaKind) -> BoxedStrategy<serde_json::Value> {
-    match schema_kind {
-        SchemaKind::Any(_any) => any::<String>().prop_map_into::<serde_json::Value>().boxed(),
-        SchemaKind::Type(schema_type) => schema_type_to_json(schema_type).boxed(),
-        // TODO: AllOf should generate all schemas and merge them to one json object
-        SchemaKind::AllOf { all_of: schemas }
-        | SchemaKind::AnyOf { any_of: schemas }
-        | SchemaKind::OneOf { one_of: schemas } => Union::new(
-            schemas
-                .iter()
-                .map(|ref_of_schema| schema_kind_to_json(&ref_of_schema.to_item_ref().schema_kind)),
-        )
-        .boxed(),
+/// Generates values of a request body that break one of the constraints of its
+/// schema. Only JSON bodies keep the types of values.
+fn body_violations(
+    media_type_name: &str,
+    media_type: &MediaType,
+    ctx: &GenerationContext,
+) -> Violations<serde_json::Value> {
+    let essence = essence(media_type_name);
+    let is_json = essence.contains("json");
+    let is_text = essence == "application/x-www-form-urlencoded"
+        || essence.starts_with("multipart/")
+        || essence.contains("xml")
+        || essence.starts_with("text/");
+    match &media_type.schema {
+        Some(schema) if is_json || is_text => schema_violations(schema, ctx, !is_json, 0),
+        _ => vec![],
     }
 }
 
-fn any_json(schema_kind: &SchemaKind) -> impl Strategy<Value = serde_json::Value> {
-    schema_kind_to_json(schema_kind)
-}
-
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
-struct OptionalJSON(Option<serde_json::Value>);
-
-impl Arbitrary for OptionalJSON {
-    type Parameters = Rc<ArbitraryParameters>;
-
-    fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
-        if let Some(ref_or_body) = args.operation.request_body.as_ref() {
-            let request_body = ref_or_body.to_item_ref();
-            for (media_type_name, media_type) in &request_body.content {
-                if media_type_name.contains("json") {
-                    match media_type
-                        .schema
-                        .as_ref()
-                        .map(|schema| any_json(&schema.to_item_ref().schema_kind))
-                    {
-                        Some(strategy) => {
-                            return strategy.prop_map(|json| OptionalJSON(Some(json))).boxed();
-                        }
-                        None => continue,
-                    };
-                };
+/// Percent-encodes the characters that are not allowed in header values.
+fn to_header_value(value: &str) -> String {
+    let mut header_value = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            ' ' | '\t' | '!'..='~' => header_value.push(c),
+            _ => {
+                let mut buffer = [0; 4];
+                for byte in c.encode_utf8(&mut buffer).bytes() {
+                    header_value.push_str(&format!("%{byte:02X}"));
+                }
             }
-        };
-
-        Just(OptionalJSON(None)).boxed()
+        }
     }
+    header_value
+}
 
-    type Strategy = BoxedStrategy<OptionalJSON>;
+/// Joins the attacks of the parameters of a location, each once.
+fn join_attacks(attacks: impl IntoIterator<Item = Attacks>) -> Attacks {
+    let mut joined: Attacks = vec![];
+    for attack in attacks.into_iter().flatten() {
+        if !joined.contains(&attack) {
+            joined.push(attack);
+        }
+    }
+    joined
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct Headers(Vec<(String, String)>);
 
-impl Arbitrary for Headers {
-    type Parameters = Rc<ArbitraryParameters>;
-
-    fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
-        args.operation
-            .parameters
-            .iter()
-            .filter_map(|ref_or_param| match ref_or_param.to_item_ref() {
-                Parameter::Header { parameter_data, .. } => {
-                    Some((Just(parameter_data.name.clone()), "[!-~ \t]*"))
-                }
-                _ => None,
-            })
-            .collect::<Vec<_>>()
-            .prop_map(Headers)
-            .boxed()
-    }
-    type Strategy = BoxedStrategy<Headers>;
+fn generate_headers(args: &ArbitraryParameters) -> BoxedStrategy<(Headers, Attacks)> {
+    args.operation
+        .parameters
+        .iter()
+        .filter_map(|ref_or_param| match ref_or_param.to_item_ref() {
+            Parameter::Header { parameter_data, .. } => {
+                let name = parameter_data.name.clone();
+                let explode = parameter_data.explode.unwrap_or(false);
+                Some(
+                    args.parameter("header", parameter_data)
+                        .prop_map(move |(value, attacks)| {
+                            let header = value.map(|value| {
+                                let value = style::simple_parameter(&value, explode);
+                                (name.clone(), to_header_value(&value))
+                            });
+                            (header, attacks)
+                        }),
+                )
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .prop_map(|headers| {
+            let (headers, attacks): (Vec<_>, Vec<_>) = headers.into_iter().unzip();
+            let headers = Headers(headers.into_iter().flatten().collect());
+            (headers, join_attacks(attacks))
+        })
+        .boxed()
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct PathParams(Vec<(String, String)>);
 
-impl Arbitrary for PathParams {
-    type Parameters = Rc<ArbitraryParameters>;
-
-    fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
-        let mut path_params = vec![];
-        for ref_or_param in &args.operation.parameters {
-            match ref_or_param.to_item_ref() {
-                Parameter::Path { parameter_data, .. } => {
-                    path_params.push((Just(parameter_data.name.clone()), any::<String>()));
-                }
-                _ => continue,
+fn generate_path_params(args: &ArbitraryParameters) -> BoxedStrategy<(PathParams, Attacks)> {
+    let mut path_params = vec![];
+    for ref_or_param in &args.operation.parameters {
+        match ref_or_param.to_item_ref() {
+            Parameter::Path {
+                parameter_data,
+                style,
+            } => {
+                let name = parameter_data.name.clone();
+                let style = style.clone();
+                let explode = parameter_data.explode.unwrap_or(false);
+                path_params.push(args.parameter("path", parameter_data).prop_map(
+                    move |(value, attacks)| {
+                        let path_param = value.map(|value| {
+                            let value = style::path_parameter(&name, &value, &style, explode);
+                            (name.clone(), value)
+                        });
+                        (path_param, attacks)
+                    },
+                ));
             }
+            _ => continue,
         }
-        path_params.prop_map(PathParams).boxed()
     }
-    type Strategy = BoxedStrategy<PathParams>;
+    path_params
+        .prop_map(|path_params| {
+            let (path_params, attacks): (Vec<_>, Vec<_>) = path_params.into_iter().unzip();
+            let path_params = PathParams(path_params.into_iter().flatten().collect());
+            (path_params, join_attacks(attacks))
+        })
+        .boxed()
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct QueryParams(Vec<(String, String)>);
 
-impl Arbitrary for QueryParams {
-    type Parameters = Rc<ArbitraryParameters>;
+fn generate_query_params(args: &ArbitraryParameters) -> BoxedStrategy<(QueryParams, Attacks)> {
+    let mut query_params = vec![];
+    for ref_or_param in &args.operation.parameters {
+        match ref_or_param.to_item_ref() {
+            Parameter::Query {
+                parameter_data,
+                style,
+                ..
+            } => {
+                let name = parameter_data.name.clone();
+                let style = style.clone();
+                let explode = parameter_data.explode.unwrap_or(style == QueryStyle::Form);
+                query_params.push(args.parameter("query", parameter_data).prop_map(
+                    move |(value, attacks)| match value {
+                        Some(value) => (
+                            style::query_parameter(&name, &value, &style, explode),
+                            attacks,
+                        ),
+                        None => (vec![], attacks),
+                    },
+                ));
+            }
+            _ => continue,
+        }
+    }
+    query_params
+        .prop_map(|query_params| {
+            let (query_params, attacks): (Vec<_>, Vec<_>) = query_params.into_iter().unzip();
+            let query_params = QueryParams(query_params.into_iter().flatten().collect());
+            (query_params, join_attacks(attacks))
+        })
+        .boxed()
+}
 
-    fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
-        let mut query_params = vec![];
-        for ref_or_param in &args.operation.parameters {
-            match ref_or_param.to_item_ref() {
-                Parameter::Query { parameter_data, .. } => {
-                    query_params.push((Just(parameter_data.name.clone()), any::<String>()));
-                }
-                _ => continue,
+/// Percent-encodes the characters that would end a cookie or are not allowed in
+/// headers.
+fn to_cookie_value(value: &str) -> String {
+    to_header_value(value).replace(';', "%3B")
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Cookies(Vec<(String, String)>);
+
+fn generate_cookies(args: &ArbitraryParameters) -> BoxedStrategy<(Cookies, Attacks)> {
+    let mut cookies = vec![];
+    for ref_or_param in &args.operation.parameters {
+        match ref_or_param.to_item_ref() {
+            Parameter::Cookie { parameter_data, .. } => {
+                let name = parameter_data.name.clone();
+                let explode = parameter_data.explode.unwrap_or(true);
+                cookies.push(args.parameter("cookie", parameter_data).prop_map(
+                    move |(value, attacks)| match value {
+                        Some(value) => {
+                            let cookies = style::cookie_parameter(&name, &value, explode)
+                                .into_iter()
+                                .map(|(name, value)| (name, to_cookie_value(&value)))
+                                .collect();
+                            (cookies, attacks)
+                        }
+                        None => (vec![], attacks),
+                    },
+                ));
             }
+            _ => continue,
         }
-        query_params.prop_map(QueryParams).boxed()
     }
-    type Strategy = BoxedStrategy<QueryParams>;
+    cookies
+        .prop_map(|cookies| {
+            let (cookies, attacks): (Vec<Vec<_>>, Vec<_>) = cookies.into_iter().unzip();
+            let cookies = Cookies(cookies.into_iter().flatten().collect());
+            (cookies, join_attacks(attacks))
+        })
+        .boxed()
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -177,8 +2071,16 @@ pub struct Payload {
     query_params: QueryParams,
     path_params: PathParams,
     headers: Headers,
-    body: OptionalJSON,
-    // TODO: add cookies
+    // Findings saved before cookies were generated do not have them
+    #[serde(default)]
+    cookies: Cookies,
+    body: OptionalBody,
+    /// Constraint of the specification that the request breaks in negative mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    violation: Option<String>,
+    /// Classes of the attack payloads sent in the request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    attacks: Vec<String>,
 }
 
 impl Arbitrary for Payload {
@@ -186,24 +2088,112 @@ impl Arbitrary for Payload {
     type Strategy = BoxedStrategy<Payload>;
 
     fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
-        let args = args;
-        any_with::<(QueryParams, PathParams, Headers, OptionalJSON)>((
-            args.clone(),
-            args.clone(),
-            args.clone(),
-            args,
-        ))
-        .prop_map(|(query_params, path_params, headers, body)| Payload {
-            query_params,
-            path_params,
-            headers,
-            body,
-        })
-        .boxed()
+        (
+            generate_query_params(&args),
+            generate_path_params(&args),
+            generate_headers(&args),
+            generate_cookies(&args),
+            generate_optional_body(&args),
+        )
+            .prop_map(
+                |(
+                    (query_params, query_attacks),
+                    (path_params, path_attacks),
+                    (headers, header_attacks),
+                    (cookies, cookie_attacks),
+                    (body, body_attacks),
+                )| Payload {
+                    query_params,
+                    path_params,
+                    headers,
+                    cookies,
+                    body,
+                    violation: None,
+                    attacks: join_attacks([
+                        query_attacks,
+                        path_attacks,
+                        header_attacks,
+                        cookie_attacks,
+                        body_attacks,
+                    ]),
+                },
+            )
+            .boxed()
     }
 }
 
 impl Payload {
+    /// Generates requests that each break exactly one constraint of the operation,
+    /// e.g. by leaving out a required parameter or sending a number out of range.
+    /// Returns `None` if the operation has no constraints to break.
+    pub fn negative(args: &ArbitraryParameters) -> Option<BoxedStrategy<Payload>> {
+        let ctx = &args.context;
+        let mut sites = vec![];
+        for ref_or_param in &args.operation.parameters {
+            let parameter = ref_or_param.to_item_ref();
+            let (location, parameter_data) = match parameter {
+                Parameter::Query { parameter_data, .. } => ("query", parameter_data),
+                Parameter::Header { parameter_data, .. } => ("header", parameter_data),
+                Parameter::Path { parameter_data, .. } => ("path", parameter_data),
+                Parameter::Cookie { parameter_data, .. } => ("cookie", parameter_data),
+            };
+            let target = Target::Parameter {
+                location,
+                name: parameter_data.name.clone(),
+            };
+            let described = format!("{location} parameter `{}`", parameter_data.name);
+            // Path parameters cannot be left out of the path
+            if parameter_data.required && location != "path" {
+                sites.push((format!("required {described}"), target.clone(), None));
+            }
+            for (constraint, value) in parameter_violations(parameter_data, ctx) {
+                sites.push((
+                    format!("{described}: {constraint}"),
+                    target.clone(),
+                    Some(value),
+                ));
+            }
+        }
+        if let Some(ReferenceOr::Item(request_body)) = &args.operation.request_body {
+            if request_body.required {
+                let target = Target::Body {
+                    media_type: String::new(),
+                };
+                sites.push(("required request body".to_owned(), target, None));
+            }
+            for (media_type_name, media_type) in &request_body.content {
+                let target = Target::Body {
+                    media_type: media_type_name.clone(),
+                };
+                for (constraint, value) in body_violations(media_type_name, media_type, ctx) {
+                    sites.push((
+                        format!("request body `{media_type_name}`: {constraint}"),
+                        target.clone(),
+                        Some(value),
+                    ));
+                }
+            }
+        }
+
+        if sites.is_empty() {
+            return None;
+        }
+        let payloads = sites.into_iter().map(|(violation, target, value)| {
+            let args = ArbitraryParameters {
+                operation: args.operation.clone(),
+                context: ctx.clone(),
+                violated: Some(Violated { target, value }),
+            };
+            any_with::<Payload>(Rc::new(args))
+                .prop_map(move |payload| Payload {
+                    violation: Some(violation.clone()),
+                    ..payload
+                })
+                .boxed()
+        });
+        Some(Union::new(payloads).boxed())
+    }
+
     pub fn query_params(&self) -> &[(String, String)] {
         &self.query_params.0
     }
@@ -216,9 +2206,31 @@ impl Payload {
         &self.headers.0
     }
 
-    pub fn body(&self) -> Option<&serde_json::Value> {
+    pub fn cookies(&self) -> &[(String, String)] {
+        &self.cookies.0
+    }
+
+    pub fn body(&self) -> Option<&Body> {
         self.body.0.as_ref()
     }
+
+    pub fn violation(&self) -> Option<&str> {
+        self.violation.as_deref()
+    }
+
+    pub fn attacks(&self) -> &[String] {
+        &self.attacks
+    }
+
+    /// Name of the mutation that made the body malformed
+    pub fn malformation(&self) -> Option<&str> {
+        self.body
+            .0
+            .as_ref()?
+            .malformed
+            .as_ref()
+            .map(|m| m.mutation.as_str())
+    }
 }
 
 #[cfg(test)]
@@ -228,10 +2240,10 @@ mod test {
     use indexmap::indexmap;
     use openapiv3::{
         HeaderStyle, IntegerType, ParameterData, ParameterSchemaOrContent, ReferenceOr, Schema,
-        StringType,
+        SchemaData, StringType,
     };
     use proptest::{
-        prop_assert, proptest,
+        prop_assert, prop_assert_eq, proptest,
         test_runner::{Config, FileFailurePersistence, TestError, TestRunner},
     };
 
@@ -243,7 +2255,13 @@ mod test {
         });
 
         let result = runner.run(
-            &any_json(&SchemaKind::Type(Type::String(StringType::default()))),
+            &ref_or_schema_to_json(
+                &ReferenceOr::Item(Schema {
+                    schema_kind: SchemaKind::Type(Type::String(StringType::default())),
+                    schema_data: Default::default(),
+                }),
+                &GenerationConfig::default().into(),
+            ),
             |s| {
                 if let serde_json::Value::String(str) = s {
                     assert!(!serde_json::from_str::<String>(&str).unwrap().is_empty())
@@ -267,30 +2285,39 @@ mod test {
             ..Config::default()
         });
 
-        let s: SchemaKind = SchemaKind::Type(Type::Object(ObjectType {
-            properties: indexmap! {
-                "date".to_string() => ReferenceOr::Item(Box::new(Schema {
-                    schema_kind: SchemaKind::Type(Type::String(StringType::default())),
-                    schema_data: Default::default(),
-                })),
-                "temperatureC".to_string() => ReferenceOr::Item(Box::new(Schema {
-                    schema_kind: SchemaKind::Type(Type::Integer(IntegerType::default())),
-                    schema_data: Default::default(),
-                })),
-            },
-            ..Default::default()
-        }));
+        let s = Schema {
+            schema_kind: SchemaKind::Type(Type::Object(ObjectType {
+                properties: indexmap! {
+                    "date".to_string() => ReferenceOr::Item(Box::new(Schema {
+                        schema_kind: SchemaKind::Type(Type::String(StringType::default())),
+                        schema_data: Default::default(),
+                    })),
+                    "temperatureC".to_string() => ReferenceOr::Item(Box::new(Schema {
+                        schema_kind: SchemaKind::Type(Type::Integer(IntegerType::default())),
+                        schema_data: Default::default(),
+                    })),
+                },
+                required: vec!["temperatureC".to_string()],
+                ..Default::default()
+            })),
+            schema_data: Default::default(),
+        };
 
-        let result = runner.run(&any_json(&s), |obj| {
-            if let serde_json::Value::Object(map) = obj {
-                assert!(map.get("temperatureC").unwrap().as_i64() >= Some(0));
-            }
-            Ok(())
-        });
+        let result = runner.run(
+            &ref_or_schema_to_json(&ReferenceOr::Item(s), &GenerationConfig::default().into()),
+            |obj| {
+                if let serde_json::Value::Object(map) = obj {
+                    let temperature = map.get("temperatureC").and_then(serde_json::Value::as_i64);
+                    assert!(temperature.is_none_or(|temperature| temperature >= 0));
+                }
+                Ok(())
+            },
+        );
 
         match result {
             Err(TestError::Fail(_, value)) => {
                 println!("Found minimal failing case: {value}");
+                assert!(value["temperatureC"].as_i64() < Some(0));
                 Ok(())
             }
             result => panic!("Unexpected result: {:?}", result),
@@ -315,21 +2342,476 @@ mod test {
             })],
             ..Default::default()
         };
-        Headers::arbitrary_with(Rc::new(ArbitraryParameters { operation }))
+        generate_headers(&ArbitraryParameters::new(
+            operation,
+            GenerationConfig::default().into(),
+        ))
+        .prop_map(|(headers, _)| headers)
+        .boxed()
+    }
+
+    fn get_path_params() -> BoxedStrategy<PathParams> {
+        let operation = Operation {
+            parameters: vec![ReferenceOr::Item(Parameter::Path {
+                parameter_data: ParameterData {
+                    name: "userId".to_owned(),
+                    description: None,
+                    required: true,
+                    deprecated: None,
+                    format: ParameterSchemaOrContent::Schema(ReferenceOr::Item(Schema {
+                        schema_kind: SchemaKind::Type(Type::Integer(IntegerType {
+                            minimum: Some(1),
+                            maximum: Some(100),
+                            ..Default::default()
+                        })),
+                        schema_data: Default::default(),
+                    })),
+                    example: None,
+                    examples: Default::default(),
+                    explode: None,
+                    extensions: Default::default(),
+                },
+                style: Default::default(),
+            })],
+            ..Default::default()
+        };
+        generate_path_params(&ArbitraryParameters::new(
+            operation,
+            config_with_violation_rate(0.).into(),
+        ))
+        .prop_map(|(path_params, _)| path_params)
+        .boxed()
+    }
+
+    fn negative_payloads() -> BoxedStrategy<Payload> {
+        let operation: Operation = serde_json::from_value(serde_json::json!({
+            "parameters": [{
+                "name": "limit",
+                "in": "query",
+                "required": true,
+                "schema": { "type": "integer" }
+            }],
+            "responses": {}
+        }))
+        .unwrap();
+        Payload::negative(&ArbitraryParameters::new(
+            operation,
+            GenerationConfig::default().into(),
+        ))
+        .unwrap()
     }
 
     fn is_valid_header_value_char(b: u8) -> bool {
-        match b {
-            b' ' | b'\t' | 33..=126 => true,
-            _ => false,
+        matches!(b, b' ' | b'\t' | 33..=126)
+    }
+
+    fn tree_context(max_depth: usize) -> GenerationContext {
+        let node = serde_json::from_value(serde_json::json!({
+            "type": "object",
+            "required": ["name", "children"],
+            "properties": {
+                "name": { "type": "string" },
+                "children": {
+                    "type": "array",
+                    "items": { "$ref": "#/components/schemas/Node" }
+                },
+                "parent": { "$ref": "#/components/schemas/Node" }
+            }
+        }))
+        .unwrap();
+        GenerationContext::new(
+            GenerationConfig {
+                violation_rate: 0.,
+                max_depth,
+                ..Default::default()
+            },
+            Rc::new(Resolver::new(
+                indexmap! {
+                    "Node".to_owned() => ReferenceOr::Item(node),
+                },
+                Default::default(),
+            )),
+        )
+    }
+
+    fn openapi31_context() -> GenerationContext {
+        let mut document = serde_json::json!({
+            "components": { "schemas": { "Point": {
+                "type": "object",
+                "required": ["kind", "position"],
+                "properties": {
+                    "kind": { "const": "point" },
+                    "position": {
+                        "type": "array",
+                        "prefixItems": [{ "type": "number" }, { "type": "string", "enum": ["m"] }],
+                        "items": false
+                    }
+                },
+                "additionalProperties": false
+            }, "Never": false }}
+        });
+        let keywords = crate::openapi31::convert(&mut document).unwrap();
+        let schemas = serde_json::from_value(document["components"]["schemas"].take()).unwrap();
+        GenerationContext::new(
+            config_with_violation_rate(0.),
+            Rc::new(Resolver::new(schemas, keywords)),
+        )
+    }
+
+    fn nesting(value: &serde_json::Value) -> usize {
+        match value {
+            serde_json::Value::Array(items) => 1 + items.iter().map(nesting).max().unwrap_or(0),
+            serde_json::Value::Object(object) => {
+                1 + object.values().map(nesting).max().unwrap_or(0)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Picks the values breaking the named constraint.
+    fn violation<T>(
+        (_, invalid): (BoxedStrategy<T>, Violations<T>),
+        constraint: &str,
+    ) -> BoxedStrategy<T> {
+        invalid
+            .into_iter()
+            .find(|(name, _)| name == constraint)
+            .map(|(_, values)| values)
+            .unwrap()
+    }
+
+    fn config_with_violation_rate(violation_rate: f64) -> GenerationConfig {
+        GenerationConfig {
+            violation_rate,
+            attack_rate: 0.,
+            malformed_rate: 0.,
+            ..Default::default()
         }
     }
 
+    fn valid_strings(string_type: StringType) -> BoxedStrategy<String> {
+        generate_string(&string_type, &config_with_violation_rate(0.))
+    }
+
+    #[test]
+    fn test_pattern_to_regex() {
+        assert_eq!(pattern_to_regex("^\\d{3}-\\w+$"), "[0-9]{3}-[A-Za-z0-9_]+");
+        assert_eq!(pattern_to_regex("a\\$"), "a\\$");
+        assert_eq!(pattern_to_regex("\\\\d"), "\\\\d");
+        assert_eq!(
+            bound_repetitions("[a+]+-\\d*x{2,}y{3}", 40),
+            "[a+]{1,40}-\\d{0,40}x{2,40}y{3}"
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let operation: Operation = serde_json::from_value(serde_json::json!({
+            "parameters": [
+                { "name": "id", "in": "query", "schema": { "type": "string", "pattern": "^\\d+$" } },
+                { "name": "code", "in": "query", "schema": { "type": "string", "pattern": "^(?!x)[a-z]+$" } }
+            ],
+            "requestBody": { "content": { "application/json": { "schema": {
+                "allOf": [{ "type": "string" }, { "type": "boolean" }]
+            } } } },
+            "responses": {}
+        }))
+        .unwrap();
+        let args = ArbitraryParameters::new(operation, GenerationConfig::default().into());
+        assert_eq!(
+            args.unsupported(),
+            [
+                Unsupported::Pattern("^(?!x)[a-z]+$".to_owned()),
+                Unsupported::AllOf(
+                    "in allOf schema #1: incompatible types `string` and `boolean`".to_owned()
+                )
+            ]
+        );
+
+        let operation: Operation = serde_json::from_value(serde_json::json!({
+            "parameters": [
+                { "name": "id", "in": "query", "schema": { "$ref": "#/components/schemas/Never" } },
+                { "name": "tag", "in": "query", "schema": { "$ref": "#/components/schemas/Tag" } }
+            ],
+            "responses": {}
+        }))
+        .unwrap();
+        let args = ArbitraryParameters::new(operation, openapi31_context());
+        assert_eq!(
+            args.unsupported(),
+            [
+                Unsupported::False("#/components/schemas/Never".to_owned()),
+                Unsupported::Reference(
+                    "Unable to resolve reference `#/components/schemas/Tag`: no such component"
+                        .to_owned()
+                )
+            ]
+        );
+    }
+
     proptest! {
         #[test]
         fn test_headers(headers in get_headers()) {
             println!("{:?}",headers);
             prop_assert!(headers.0.iter().all(|(_, v)| v.bytes().all(is_valid_header_value_char)));
         }
+
+        #[test]
+        fn test_typed_path_params(params in get_path_params()) {
+            let user_id: i64 = params.0[0].1.parse().unwrap();
+            prop_assert!((1..=100).contains(&user_id));
+        }
+
+        #[test]
+        fn test_integer_bounds(n in generate_integer(&IntegerType {
+            minimum: Some(-10),
+            maximum: Some(100),
+            exclusive_maximum: true,
+            multiple_of: Some(3),
+            ..Default::default()
+        }, &config_with_violation_rate(0.))) {
+            let n = n.as_i64().unwrap();
+            prop_assert!((-10..100).contains(&n));
+            prop_assert_eq!(n % 3, 0);
+        }
+
+        #[test]
+        fn test_integer_multiple_of_violations(n in violation(integer_strategies(&IntegerType {
+            minimum: Some(0),
+            maximum: Some(9),
+            multiple_of: Some(3),
+            ..Default::default()
+        }), "multipleOf 3")) {
+            let n = n.as_i64().unwrap();
+            prop_assert!((0..=9).contains(&n) && n % 3 != 0);
+        }
+
+        #[test]
+        fn test_number_multiple_of_violations(n in violation(number_strategies(&NumberType {
+            minimum: Some(0.),
+            maximum: Some(1.),
+            multiple_of: Some(0.25),
+            ..Default::default()
+        }), "multipleOf 0.25")) {
+            let n = n.as_f64().unwrap();
+            prop_assert!((0. ..=1.).contains(&n) && (n / 0.25).fract() != 0.);
+        }
+
+        #[test]
+        fn test_int32_violations(n in generate_integer(&IntegerType {
+            format: VariantOrUnknownOrEmpty::Item(IntegerFormat::Int32),
+            minimum: Some(0),
+            ..Default::default()
+        }, &config_with_violation_rate(1.))) {
+            let n = n.as_i64().unwrap();
+            prop_assert!(n < 0 || n > i32::MAX as i64);
+        }
+
+        #[test]
+        fn test_number_bounds(n in generate_number(&NumberType {
+            minimum: Some(0.),
+            maximum: Some(1.),
+            exclusive_minimum: true,
+            ..Default::default()
+        }, &config_with_violation_rate(0.))) {
+            let n = n.as_f64().unwrap();
+            prop_assert!(n > 0. && n <= 1.);
+        }
+
+        #[test]
+        fn test_optional_parameters(payload in any_with::<Payload>(Rc::new(ArbitraryParameters::new(
+            serde_json::from_value(serde_json::json!({
+                "parameters": [
+                    { "name": "x-required", "in": "header", "required": true, "schema": { "type": "string" } },
+                    { "name": "x-optional", "in": "header", "schema": { "type": "string" } },
+                    { "name": "optional", "in": "query", "schema": { "type": "string" } }
+                ],
+                "responses": {}
+            })).unwrap(),
+            GenerationConfig {
+                optional_rate: 0.,
+                ..Default::default()
+            }.into(),
+        )))) {
+            let names: Vec<_> = payload.headers().iter().map(|(name, _)| name.as_str()).collect();
+            prop_assert_eq!(names, ["x-required"]);
+            prop_assert!(payload.query_params().is_empty());
+        }
+
+        #[test]
+        fn test_attack_classes(payload in any_with::<Payload>(Rc::new(ArbitraryParameters::new(
+            serde_json::from_value(serde_json::json!({
+                "parameters": [{ "name": "q", "in": "header", "required": true, "schema": { "type": "string" } }],
+                "responses": {}
+            })).unwrap(),
+            GenerationConfig {
+                attack_rate: 1.,
+                ..Default::default()
+            }.into(),
+        )))) {
+            prop_assert_eq!(payload.attacks().len(), 1, "{:?}", payload);
+            // The marked class is not sent
+            prop_assert!(!payload.headers()[0].1.contains("%EF%B7%90"), "{:?}", payload);
+        }
+
+        #[test]
+        fn test_string_enum(s in generate_string(&StringType {
+            enumeration: vec!["active".to_owned(), "archived".to_owned()],
+            ..Default::default()
+        }, &config_with_violation_rate(0.))) {
+            prop_assert!(s == "active" || s == "archived");
+        }
+
+        #[test]
+        fn test_string_enum_violations(s in generate_string(&StringType {
+            enumeration: vec!["active".to_owned(), "archived".to_owned()],
+            ..Default::default()
+        }, &config_with_violation_rate(1.))) {
+            prop_assert!(s != "active" && s != "archived");
+        }
+
+        #[test]
+        fn test_object_properties(object in generate_json_object(&ObjectType {
+            properties: indexmap! {
+                "id".to_owned() => ReferenceOr::boxed_item(Schema {
+                    schema_kind: SchemaKind::Type(Type::Integer(IntegerType::default())),
+                    schema_data: Default::default(),
+                }),
+                "name".to_owned() => ReferenceOr::boxed_item(Schema {
+                    schema_kind: SchemaKind::Type(Type::String(StringType::default())),
+                    schema_data: Default::default(),
+                }),
+                "tag".to_owned() => ReferenceOr::boxed_item(Schema {
+                    schema_kind: SchemaKind::Type(Type::String(StringType::default())),
+                    schema_data: Default::default(),
+                }),
+            },
+            required: vec!["id".to_owned()],
+            additional_properties: Some(AdditionalProperties::Any(false)),
+            min_properties: Some(2),
+            max_properties: Some(2),
+        }, &config_with_violation_rate(0.).into())) {
+            let object = object.as_object().unwrap();
+            prop_assert_eq!(object.len(), 2);
+            prop_assert!(object.contains_key("id"));
+        }
+
+        #[test]
+        fn test_read_only_and_nullable(object in generate_json_object(&ObjectType {
+            properties: indexmap! {
+                "id".to_owned() => ReferenceOr::boxed_item(Schema {
+                    schema_kind: SchemaKind::Type(Type::Integer(IntegerType::default())),
+                    schema_data: SchemaData {
+                        read_only: true,
+                        ..Default::default()
+                    },
+                }),
+                "name".to_owned() => ReferenceOr::boxed_item(Schema {
+                    schema_kind: SchemaKind::Type(Type::String(StringType::default())),
+                    schema_data: SchemaData {
+                        nullable: true,
+                        ..Default::default()
+                    },
+                }),
+            },
+            required: vec!["id".to_owned(), "name".to_owned()],
+            additional_properties: Some(AdditionalProperties::Any(false)),
+            ..Default::default()
+        }, &GenerationConfig {
+            violation_rate: 0.,
+            null_rate: 1.,
+            ..Default::default()
+        }.into())) {
+            prop_assert_eq!(object, serde_json::json!({ "name": null }));
+        }
+
+        #[test]
+        fn test_openapi31_keywords(point in ref_or_schema_to_json(
+            &ReferenceOr::<Schema>::ref_("#/components/schemas/Point"),
+            &openapi31_context(),
+        )) {
+            prop_assert_eq!(&point["kind"], "point");
+            let position = point["position"].as_array().unwrap();
+            prop_assert_eq!(position.len(), 2);
+            prop_assert!(position[0].is_number());
+            prop_assert_eq!(&position[1], "m");
+        }
+
+        #[test]
+        fn test_recursive_schema(node in ref_or_schema_to_json(
+            &ReferenceOr::<Schema>::ref_("#/components/schemas/Node"),
+            &tree_context(3),
+        )) {
+            prop_assert!(node.is_object());
+            prop_assert!(nesting(&node) <= 6, "{} is nested too deep", node);
+        }
+
+        #[test]
+        fn test_negative_payloads(payload in negative_payloads()) {
+            match payload.violation() {
+                Some("required query parameter `limit`") => {
+                    prop_assert!(payload.query_params().is_empty())
+                }
+                Some("query parameter `limit`: type `integer`") => {
+                    prop_assert!(payload.query_params()[0].1.parse::<i64>().is_err())
+                }
+                violation => prop_assert!(false, "unexpected violation {:?}", violation),
+            }
+        }
+
+        #[test]
+        fn test_parameter_examples(value in generate_parameter(
+            &serde_json::from_value::<ParameterData>(serde_json::json!({
+                "name": "limit",
+                "schema": { "type": "integer", "default": 20 },
+                "example": 10,
+                "examples": { "max": { "value": 100 } }
+            })).unwrap(),
+            &GenerationConfig {
+                violation_rate: 0.,
+                example_rate: 1.,
+                ..Default::default()
+            }.into(),
+        )) {
+            prop_assert!([10, 100, 20].contains(&value.as_i64().unwrap()), "{}", value);
+        }
+
+        #[test]
+        fn test_string_length(s in valid_strings(StringType {
+            min_length: Some(3),
+            max_length: Some(5),
+            ..Default::default()
+        })) {
+            prop_assert!((3..=5).contains(&s.chars().count()));
+        }
+
+        #[test]
+        fn test_string_pattern(s in valid_strings(StringType {
+            pattern: Some("^[A-Z]{2}\\d+$".to_owned()),
+            max_length: Some(6),
+            ..Default::default()
+        })) {
+            prop_assert!(s.len() <= 6);
+            prop_assert!(s[..2].bytes().all(|b| b.is_ascii_uppercase()));
+            prop_assert!(s[2..].bytes().all(|b| b.is_ascii_digit()));
+        }
+
+        #[test]
+        fn test_string_pattern_longer_than_repetitions(s in valid_strings(StringType {
+            pattern: Some("^[a-z]+$".to_owned()),
+            min_length: Some(40),
+            ..Default::default()
+        })) {
+            prop_assert!(s.len() >= 40);
+            prop_assert!(s.bytes().all(|b| b.is_ascii_lowercase()));
+        }
+
+        #[test]
+        fn test_string_pattern_violation(s in Union::new(string_strategies(&StringType {
+            pattern: Some("^[a-z]*$".to_owned()),
+            ..Default::default()
+        }, &GenerationConfig::default()).1.into_iter().map(|(_, strategy)| strategy))) {
+            prop_assert!(!s.bytes().all(|b| b.is_ascii_lowercase()));
+        }
     }
 }
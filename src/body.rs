@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Separates the parts of multipart bodies
+const BOUNDARY: &str = "openapi-fuzzer-0a7d3c9e51f4b862";
+
+/// Request body with the media type it is sent as
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Body {
+    pub content_type: String,
+    #[serde(flatten)]
+    pub content: Content,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub malformed: Option<Malformed>,
+}
+
+/// Bytes sent instead of the encoded content to check how the server handles
+/// bodies that are not well-formed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Malformed {
+    /// Name of the mutation, e.g. `truncated`
+    pub mutation: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bytes: Vec<u8>,
+    /// Content type sent instead of the one of the body
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Depth of nested JSON arrays sent instead of the bytes, which would make
+    /// findings huge
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nesting: Option<usize>,
+}
+
+impl Malformed {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self.nesting {
+            Some(depth) => ["[".repeat(depth), "]".repeat(depth)].concat().into_bytes(),
+            None => self.bytes.clone(),
+        }
+    }
+}
+
+/// Content of a request body in one of the supported encodings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "encoding", content = "content", rename_all = "kebab-case")]
+pub enum Content {
+    Json(Value),
+    FormUrlencoded(Vec<(String, String)>),
+    Multipart(Vec<Part>),
+    Text(String),
+    Xml(String),
+    Binary(Vec<u8>),
+}
+
+/// Part of a `multipart/form-data` body
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Part {
+    pub name: String,
+    pub content_type: String,
+    pub filename: Option<String>,
+    pub content: Vec<u8>,
+}
+
+impl Body {
+    /// Value of the `Content-Type` header to send the body with
+    pub fn content_type_header(&self) -> String {
+        if let Some(content_type) = self.malformed.as_ref().and_then(|m| m.content_type.clone()) {
+            return content_type;
+        }
+        match self.content {
+            Content::Multipart(_) => format!("{}; boundary={BOUNDARY}", self.content_type),
+            _ => self.content_type.clone(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        if let Some(malformed) = &self.malformed {
+            return malformed.to_bytes();
+        }
+        match &self.content {
+            Content::Json(json) => json.to_string().into_bytes(),
+            Content::FormUrlencoded(pairs) => url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(pairs)
+                .finish()
+                .into_bytes(),
+            Content::Multipart(parts) => multipart_to_bytes(parts),
+            Content::Text(text) | Content::Xml(text) => text.clone().into_bytes(),
+            Content::Binary(bytes) => bytes.clone(),
+        }
+    }
+}
+
+/// Quotes a name in a `Content-Disposition` header the way browsers do, so
+/// quotes and line breaks in generated names cannot inject headers.
+fn quote(name: &str) -> String {
+    format!(
+        "\"{}\"",
+        name.replace('"', "%22")
+            .replace('\r', "%0D")
+            .replace('\n', "%0A")
+    )
+}
+
+fn multipart_to_bytes(parts: &[Part]) -> Vec<u8> {
+    let mut bytes = vec![];
+    for part in parts {
+        let mut disposition = format!("form-data; name={}", quote(&part.name));
+        if let Some(filename) = &part.filename {
+            disposition.push_str(&format!("; filename={}", quote(filename)));
+        }
+        bytes.extend(
+            format!(
+                "--{BOUNDARY}\r\nContent-Disposition: {disposition}\r\nContent-Type: {}\r\n\r\n",
+                part.content_type
+            )
+            .into_bytes(),
+        );
+        bytes.extend(&part.content);
+        bytes.extend(b"\r\n");
+    }
+    bytes.extend(format!("--{BOUNDARY}--\r\n").into_bytes());
+    bytes
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Converts a JSON value to an XML element. Properties become child elements
+/// and the items of arrays are repeated elements with the name of the array.
+pub fn json_to_xml(name: &str, value: &Value) -> String {
+    match value {
+        Value::Array(items) => items.iter().map(|item| json_to_xml(name, item)).collect(),
+        Value::Object(object) => {
+            let children: String = object
+                .iter()
+                .map(|(name, value)| json_to_xml(name, value))
+                .collect();
+            format!("<{name}>{children}</{name}>")
+        }
+        Value::Null => format!("<{name}/>"),
+        Value::String(s) => format!("<{name}>{}</{name}>", escape_xml(s)),
+        value => format!("<{name}>{value}</{name}>"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_to_xml() {
+        assert_eq!(
+            json_to_xml(
+                "Pet",
+                &json!({ "name": "<Rex>", "tags": ["a", "b"], "age": 3 })
+            ),
+            "<Pet><age>3</age><name>&lt;Rex&gt;</name><tags>a</tags><tags>b</tags></Pet>"
+        );
+    }
+
+    #[test]
+    fn test_multipart() {
+        let body = Body {
+            content_type: "multipart/form-data".to_owned(),
+            content: Content::Multipart(vec![Part {
+                name: "file".to_owned(),
+                content_type: "image/png".to_owned(),
+                filename: Some("a\".png".to_owned()),
+                content: b"PNG".to_vec(),
+            }]),
+            malformed: None,
+        };
+
+        assert_eq!(
+            String::from_utf8(body.to_bytes()).unwrap(),
+            format!(
+                "--{BOUNDARY}\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"a%22.png\"\r\n\
+                Content-Type: image/png\r\n\r\nPNG\r\n--{BOUNDARY}--\r\n"
+            )
+        );
+        // The encoding is saved with findings
+        let saved = serde_json::to_value(&body).unwrap();
+        assert_eq!(saved["encoding"], "multipart");
+        assert_eq!(serde_json::from_value::<Body>(saved).unwrap(), body);
+    }
+}
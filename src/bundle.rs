@@ -0,0 +1,357 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use indexmap::IndexMap;
+use serde_json::{json, Map, Value};
+
+/// Keys whose value is a schema
+const SCHEMA_KEYS: &[&str] = &[
+    "schema",
+    "items",
+    "additionalProperties",
+    "not",
+    "contains",
+    "if",
+    "then",
+    "else",
+];
+
+/// Keys whose value is a map or an array of schemas
+const SCHEMA_MAP_KEYS: &[&str] = &[
+    "schemas",
+    "definitions",
+    "properties",
+    "patternProperties",
+    "dependentSchemas",
+    "$defs",
+    "allOf",
+    "anyOf",
+    "oneOf",
+    "prefixItems",
+];
+
+/// What a value of the document is, which decides how references in its place
+/// are bundled
+#[derive(Clone, Copy, PartialEq)]
+enum Position {
+    Schema,
+    SchemaMap,
+    Other,
+}
+
+impl Position {
+    fn of_child(self, key: &str) -> Position {
+        match self {
+            Position::SchemaMap => Position::Schema,
+            _ if SCHEMA_KEYS.contains(&key) => Position::Schema,
+            _ if SCHEMA_MAP_KEYS.contains(&key) => Position::SchemaMap,
+            _ => Position::Other,
+        }
+    }
+}
+
+/// Replaces references to other files with what they point to. Referenced
+/// schemas are moved to the schemas of the document under a unique name, as they
+/// may be recursive, and everything else is inlined. Returns whether the
+/// document referenced other files.
+pub fn bundle(path: &Path, document: &mut Value) -> Result<bool> {
+    let root = fs::canonicalize(path).context(format!("Unable to read {path:?}"))?;
+    let swagger = document.get("swagger").is_some();
+    let schemas_pointer = if swagger {
+        "/definitions"
+    } else {
+        "/components/schemas"
+    };
+    let taken = document
+        .pointer(schemas_pointer)
+        .and_then(Value::as_object)
+        .map(|schemas| schemas.keys().cloned().collect())
+        .unwrap_or_default();
+    let mut bundler = Bundler {
+        root: root.clone(),
+        schemas_pointer,
+        files: HashMap::new(),
+        names: IndexMap::new(),
+        taken,
+        bundled: Map::new(),
+        inlining: vec![],
+    };
+    bundler.walk(document, &root, path, String::new(), Position::Other)?;
+
+    let external = !bundler.files.is_empty();
+    if !bundler.bundled.is_empty() {
+        let mut schemas = document;
+        for token in schemas_pointer.split('/').skip(1) {
+            schemas = schemas
+                .as_object_mut()
+                .context(format!("Invalid {path:?}: expected an object"))?
+                .entry(token)
+                .or_insert_with(|| json!({}));
+        }
+        let schemas = schemas.as_object_mut().context(format!(
+            "Invalid {path:?}: expected an object at `{schemas_pointer}`"
+        ))?;
+        schemas.extend(bundler.bundled);
+    }
+    Ok(external)
+}
+
+struct Bundler {
+    /// Canonical path of the document that is bundled
+    root: PathBuf,
+    /// Where schemas are kept in the document, depending on its version
+    schemas_pointer: &'static str,
+    /// Referenced documents by their canonical path
+    files: HashMap<PathBuf, Value>,
+    /// Names that schemas of other files are moved to by their file and pointer
+    names: IndexMap<(PathBuf, String), String>,
+    /// Names of the schemas of the document
+    taken: HashSet<String>,
+    /// Schemas moved to the document
+    bundled: Map<String, Value>,
+    /// Values being inlined by their file and pointer, to detect cycles
+    inlining: Vec<(PathBuf, String)>,
+}
+
+impl Bundler {
+    /// Bundles the references in `value` found at `pointer` in `file`, which is
+    /// shown as `shown` in errors.
+    fn walk(
+        &mut self,
+        value: &mut Value,
+        file: &Path,
+        shown: &Path,
+        pointer: String,
+        position: Position,
+    ) -> Result<()> {
+        match value {
+            Value::Object(object) => {
+                if let Some(Value::String(reference)) = object.get("$ref") {
+                    let reference = reference.clone();
+                    if let Some(replacement) =
+                        self.reference(&reference, file, shown, &pointer, position)?
+                    {
+                        *value = replacement;
+                    }
+                    return Ok(());
+                }
+                for (key, child) in object.iter_mut() {
+                    let child_pointer = format!("{pointer}/{}", escape(key));
+                    self.walk(child, file, shown, child_pointer, position.of_child(key))?;
+                }
+            }
+            Value::Array(items) => {
+                let position = match position {
+                    Position::SchemaMap => Position::Schema,
+                    _ => Position::Other,
+                };
+                for (i, item) in items.iter_mut().enumerate() {
+                    self.walk(item, file, shown, format!("{pointer}/{i}"), position)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Returns what replaces the object with the `$ref`, or `None` to keep it.
+    fn reference(
+        &mut self,
+        reference: &str,
+        file: &Path,
+        shown: &Path,
+        pointer: &str,
+        position: Position,
+    ) -> Result<Option<Value>> {
+        let (location, target_pointer) = reference.split_once('#').unwrap_or((reference, ""));
+        let broken =
+            || format!("Unable to resolve reference `{reference}` at `{pointer}` in {shown:?}");
+        let target_shown = if location.is_empty() {
+            shown.to_owned()
+        } else {
+            shown
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .join(location)
+        };
+        let target_file = if location.is_empty() {
+            file.to_owned()
+        } else {
+            let joined = file
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .join(location);
+            fs::canonicalize(&joined)
+                .context(format!("Unable to read {target_shown:?}"))
+                .with_context(broken)?
+        };
+
+        // References into the document itself are kept
+        if target_file == self.root {
+            return Ok(if file == self.root {
+                None
+            } else {
+                Some(json!({ "$ref": format!("#{target_pointer}") }))
+            });
+        }
+        if !self.files.contains_key(&target_file) {
+            let content = fs::read_to_string(&target_file)
+                .context(format!("Unable to read {target_shown:?}"))
+                .with_context(broken)?;
+            let document: Value = serde_yaml::from_str(&content)
+                .context(format!("Failed to parse {target_shown:?}"))
+                .with_context(broken)?;
+            self.files.insert(target_file.clone(), document);
+        }
+        let mut target = match self.files[&target_file].pointer(target_pointer) {
+            Some(target) => target.clone(),
+            None => bail!(
+                "{}: {:?} has no `{}`",
+                broken(),
+                target_shown,
+                target_pointer
+            ),
+        };
+
+        let key = (target_file.clone(), target_pointer.to_owned());
+        if position == Position::Schema {
+            if let Some(name) = self.names.get(&key) {
+                return Ok(Some(self.schema_reference(name)));
+            }
+            let name = self.unique_name(&target_file, target_pointer);
+            self.names.insert(key, name.clone());
+            self.walk(
+                &mut target,
+                &target_file,
+                &target_shown,
+                target_pointer.to_owned(),
+                Position::Schema,
+            )?;
+            self.bundled.insert(name.clone(), target);
+            return Ok(Some(self.schema_reference(&name)));
+        }
+
+        if self.inlining.contains(&key) {
+            bail!("{}: the reference is cyclic", broken());
+        }
+        self.inlining.push(key);
+        self.walk(
+            &mut target,
+            &target_file,
+            &target_shown,
+            target_pointer.to_owned(),
+            position,
+        )?;
+        self.inlining.pop();
+        Ok(Some(target))
+    }
+
+    fn schema_reference(&self, name: &str) -> Value {
+        json!({ "$ref": format!("#{}/{}", self.schemas_pointer, escape(name)) })
+    }
+
+    /// Names a schema after the last token of its pointer or after its file.
+    fn unique_name(&mut self, file: &Path, pointer: &str) -> String {
+        let base = match pointer.rsplit('/').next() {
+            Some(token) if !token.is_empty() => token.replace("~1", "/").replace("~0", "~"),
+            _ => file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "schema".to_owned()),
+        };
+        let mut name = base.clone();
+        let mut n = 2;
+        while self.taken.contains(&name) {
+            name = format!("{base}_{n}");
+            n += 1;
+        }
+        self.taken.insert(name.clone());
+        name
+    }
+}
+
+fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Directory removed when the test ends, even if it fails
+    struct TempDir(PathBuf);
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_bundle() {
+        let temp = TempDir(
+            std::env::temp_dir().join(format!("openapi-fuzzer-test-bundle-{}", std::process::id())),
+        );
+        let dir = &temp.0;
+        fs::create_dir_all(dir.join("paths")).unwrap();
+        fs::create_dir_all(dir.join("schemas")).unwrap();
+        let files = [
+            (
+                "openapi.yaml",
+                "openapi: 3.0.0\npaths:\n  /pets:\n    $ref: paths/pets.yaml\ncomponents:\n  schemas:\n    Pet: { type: string }\n",
+            ),
+            (
+                "paths/pets.yaml",
+                "get:\n  parameters:\n    - $ref: '#/limit'\n  responses:\n    '200':\n      content:\n        application/json:\n          schema: { $ref: ../schemas/Pet.yaml }\nlimit: { name: limit, in: query }\n",
+            ),
+            (
+                "schemas/Pet.yaml",
+                "type: object\nproperties:\n  owner: { $ref: 'Owner.yaml#/Owner' }\n",
+            ),
+            (
+                "schemas/Owner.yaml",
+                "Owner:\n  type: object\n  properties:\n    pets: { type: array, items: { $ref: Pet.yaml } }\n",
+            ),
+        ];
+        for (name, content) in &files {
+            fs::write(dir.join(name), content).unwrap();
+        }
+
+        let path = dir.join("openapi.yaml");
+        let mut document: Value = serde_yaml::from_str(files[0].1).unwrap();
+        assert!(bundle(&path, &mut document).unwrap());
+        let get = &document["paths"]["/pets"]["get"];
+        assert_eq!(get["parameters"][0]["name"], "limit");
+        let schema = &get["responses"]["200"]["content"]["application/json"]["schema"];
+        assert_eq!(schema["$ref"], "#/components/schemas/Pet_2");
+        let schemas = &document["components"]["schemas"];
+        assert_eq!(
+            schemas["Pet_2"]["properties"]["owner"]["$ref"],
+            "#/components/schemas/Owner"
+        );
+        assert_eq!(
+            schemas["Owner"]["properties"]["pets"]["items"]["$ref"],
+            "#/components/schemas/Pet_2"
+        );
+
+        fs::write(
+            dir.join("schemas/Pet.yaml"),
+            "properties:\n  owner: { $ref: 'Owner.yaml#/Ownr' }\n",
+        )
+        .unwrap();
+        let mut document: Value = serde_yaml::from_str(files[0].1).unwrap();
+        let error = bundle(&path, &mut document).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Unable to resolve reference `Owner.yaml#/Ownr` at `/properties/owner` in {:?}: {:?} has no `/Ownr`",
+                dir.join("paths/../schemas/Pet.yaml"),
+                dir.join("paths/../schemas/Owner.yaml"),
+            )
+        );
+    }
+}
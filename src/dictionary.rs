@@ -0,0 +1,353 @@
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use proptest::{
+    sample::select,
+    strategy::{BoxedStrategy, Strategy, Union},
+};
+use serde_json::Value;
+
+/// Surrounds the class of a payload that is part of a generated value, until it
+/// is taken out before the value is sent. Noncharacters are not sent by clients.
+const MARKER: char = '\u{fdd0}';
+
+/// Built-in attack payloads by the name of their class
+const BUILT_IN: &[(&str, &[&str])] = &[
+    (
+        "sql-injection",
+        &[
+            "' OR '1'='1",
+            "' OR 1=1 --",
+            "\" OR \"\"=\"",
+            "1; DROP TABLE users --",
+            "' UNION SELECT NULL, NULL --",
+            "1' AND SLEEP(5) --",
+            "'; WAITFOR DELAY '0:0:5' --",
+            "admin'--",
+        ],
+    ),
+    (
+        "nosql-injection",
+        &[
+            "{\"$gt\": \"\"}",
+            "{\"$ne\": null}",
+            "{\"$where\": \"sleep(5000)\"}",
+            "[$ne]=1",
+            "'; return true; var a='",
+            "{\"$regex\": \".*\"}",
+        ],
+    ),
+    (
+        "path-traversal",
+        &[
+            "../../../../../../etc/passwd",
+            "..\\..\\..\\..\\windows\\win.ini",
+            "%2e%2e%2f%2e%2e%2f%2e%2e%2fetc%2fpasswd",
+            "....//....//....//etc/passwd",
+            "/etc/passwd%00",
+            "file:///etc/passwd",
+        ],
+    ),
+    (
+        "format-string",
+        &[
+            "%s%s%s%s%s",
+            "%x%x%x%x",
+            "%n%n%n%n",
+            "%99999999s",
+            "{0}{1}{2}",
+            "%@",
+        ],
+    ),
+    (
+        "template-injection",
+        &[
+            "{{7*7}}",
+            "${7*7}",
+            "<%= 7*7 %>",
+            "#{7*7}",
+            "{{constructor.constructor('return process')()}}",
+            "${T(java.lang.Runtime).getRuntime().exec('id')}",
+        ],
+    ),
+    (
+        "command-injection",
+        &[
+            "; id",
+            "| id",
+            "&& id",
+            "`id`",
+            "$(id)",
+            "; sleep 5",
+            "\nid\n",
+        ],
+    ),
+    (
+        "crlf-injection",
+        &[
+            "\r\nX-Injected: true",
+            "%0d%0aX-Injected:%20true",
+            "\r\n\r\n<html>injected</html>",
+            "\nSet-Cookie: injected=true",
+        ],
+    ),
+    (
+        "overlong-utf8",
+        &[
+            "%c0%ae%c0%ae%c0%af",
+            "%c0%bc%c0%be",
+            "%e0%80%af",
+            "%f0%80%80%af",
+        ],
+    ),
+    (
+        "huge-number",
+        &[
+            "99999999999999999999999999999999",
+            "-99999999999999999999999999999999",
+            "1e309",
+            "-1e309",
+            "9223372036854775808",
+            "-9223372036854775809",
+            "4294967296",
+            "NaN",
+            "Infinity",
+            "0x7fffffffffffffff",
+        ],
+    ),
+];
+
+/// Attack payloads of one kind, e.g. SQL injections
+#[derive(Debug, Clone)]
+pub struct AttackClass {
+    pub name: String,
+    pub weight: u32,
+    pub payloads: Vec<String>,
+}
+
+/// Attack payloads mixed into generated strings, grouped into classes
+#[derive(Debug, Clone)]
+pub struct Dictionary {
+    classes: Vec<AttackClass>,
+}
+
+impl Default for Dictionary {
+    fn default() -> Self {
+        Dictionary {
+            classes: BUILT_IN
+                .iter()
+                .map(|(name, payloads)| AttackClass {
+                    name: name.to_string(),
+                    weight: 1,
+                    payloads: payloads.iter().map(|payload| payload.to_string()).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Dictionary {
+    /// Adds the payloads of a dictionary file. Each payload is on its own line
+    /// after the `[name]` of its class, which is created if it does not exist.
+    /// Blank lines and lines starting with `#` are skipped and `\r`, `\n`, `\t`,
+    /// `\0` and `\\` are unescaped.
+    pub fn load(&mut self, path: &Path) -> Result<()> {
+        let content = fs::read_to_string(path).context(format!("Unable to read {path:?}"))?;
+        let mut class = None;
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                class = Some(self.class_index(name.trim()));
+                continue;
+            }
+            match class {
+                Some(index) => self.classes[index].payloads.push(unescape(line)),
+                None => bail!(
+                    "Invalid dictionary {:?}: payload on line {} is not in a `[class]`",
+                    path,
+                    number + 1
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    fn class_index(&mut self, name: &str) -> usize {
+        match self.classes.iter().position(|class| class.name == name) {
+            Some(index) => index,
+            None => {
+                self.classes.push(AttackClass {
+                    name: name.to_owned(),
+                    weight: 1,
+                    payloads: vec![],
+                });
+                self.classes.len() - 1
+            }
+        }
+    }
+
+    /// Sets how often payloads of a class are picked relative to other classes.
+    /// A weight of 0 disables the class.
+    pub fn set_weight(&mut self, name: &str, weight: u32) -> Result<()> {
+        match self.classes.iter_mut().find(|class| class.name == name) {
+            Some(class) => class.weight = weight,
+            None => bail!("Unknown attack class `{}`", name),
+        }
+        Ok(())
+    }
+
+    /// Picks payloads of the enabled classes together with the name of their
+    /// class, or returns `None` if there are none.
+    pub fn strategy(&self) -> Option<BoxedStrategy<(String, String)>> {
+        let classes: Vec<_> = self
+            .classes
+            .iter()
+            .filter(|class| class.weight > 0 && !class.payloads.is_empty())
+            .map(|class| {
+                let name = class.name.clone();
+                let payloads =
+                    select(class.payloads.clone()).prop_map(move |payload| (name.clone(), payload));
+                (class.weight, payloads.boxed())
+            })
+            .collect();
+        (!classes.is_empty()).then(|| Union::new_weighted(classes).boxed())
+    }
+
+    /// Takes the classes out of the strings of the value that contain marked
+    /// payloads and returns them, each once.
+    pub fn take_attacks(&self, value: &mut Value) -> Vec<String> {
+        let mut attacks = vec![];
+        self.unmark_value(value, &mut attacks);
+        attacks
+    }
+
+    fn unmark_value(&self, value: &mut Value, attacks: &mut Vec<String>) {
+        match value {
+            Value::String(s) if s.contains(MARKER) => *s = self.unmark(s, attacks),
+            Value::Array(items) => {
+                for item in items {
+                    self.unmark_value(item, attacks);
+                }
+            }
+            Value::Object(properties) => {
+                for property in properties.values_mut() {
+                    self.unmark_value(property, attacks);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Removes the class names between markers, which may be anywhere in the
+    /// string, e.g. when the value was serialized into a JSON parameter.
+    fn unmark(&self, s: &str, attacks: &mut Vec<String>) -> String {
+        let mut unmarked = String::with_capacity(s.len());
+        let mut rest = s;
+        while let Some(start) = rest.find(MARKER) {
+            let after = &rest[start + MARKER.len_utf8()..];
+            let class = after
+                .find(MARKER)
+                .map(|end| &after[..end])
+                .filter(|name| self.classes.iter().any(|class| class.name == *name));
+            match class {
+                Some(name) => {
+                    unmarked.push_str(&rest[..start]);
+                    if !attacks.iter().any(|attack| attack == name) {
+                        attacks.push(name.to_owned());
+                    }
+                    rest = &after[name.len() + MARKER.len_utf8()..];
+                }
+                None => {
+                    unmarked.push_str(&rest[..start + MARKER.len_utf8()]);
+                    rest = after;
+                }
+            }
+        }
+        unmarked.push_str(rest);
+        unmarked
+    }
+}
+
+/// Marks the payload with its class, so that the class can be taken out of the
+/// value it ends up in.
+pub fn marked(class: &str, payload: &str) -> String {
+    format!("{MARKER}{class}{MARKER}{payload}")
+}
+
+fn unescape(line: &str) -> String {
+    let mut unescaped = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('r') => unescaped.push('\r'),
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('0') => unescaped.push('\0'),
+            Some(escaped) => unescaped.push(escaped),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::{strategy::ValueTree, test_runner::TestRunner};
+
+    #[test]
+    fn test_load() {
+        let path = std::env::temp_dir().join("openapi-fuzzer-test-dictionary.txt");
+        fs::write(
+            &path,
+            "# custom payloads\n[sql-injection]\n' OR 2>1 --\n\n[ldap-injection]\n*)(uid=*\nx\\r\\ny\n",
+        )
+        .unwrap();
+        let mut dictionary = Dictionary::default();
+        dictionary.load(&path).unwrap();
+        dictionary.set_weight("ldap-injection", 3).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let ldap = dictionary.classes.last().unwrap().clone();
+        assert_eq!(ldap.name, "ldap-injection");
+        assert_eq!(ldap.weight, 3);
+        assert_eq!(ldap.payloads, ["*)(uid=*", "x\r\ny"]);
+        for class in &mut dictionary.classes {
+            class.weight = u32::from(class.name == "ldap-injection");
+        }
+        let mut runner = TestRunner::deterministic();
+        let strategy = dictionary.strategy().unwrap();
+        let (class, payload) = strategy.new_tree(&mut runner).unwrap().current();
+        assert_eq!(class, "ldap-injection");
+        assert!(ldap.payloads.contains(&payload));
+        assert!(dictionary.set_weight("xss", 1).is_err());
+    }
+
+    #[test]
+    fn test_take_attacks() {
+        let dictionary = Dictionary::default();
+        let mut value = serde_json::json!({
+            "name": marked("sql-injection", "admin'--"),
+            "tags": [format!("a{}", marked("crlf-injection", "\r\n")), "\u{fdd0}x\u{fdd0}"],
+            "query": serde_json::json!({ "q": marked("sql-injection", "1") }).to_string(),
+        });
+        assert_eq!(
+            dictionary.take_attacks(&mut value),
+            ["sql-injection", "crlf-injection"]
+        );
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "name": "admin'--",
+                "tags": ["a\r\n", "\u{fdd0}x\u{fdd0}"],
+                "query": "{\"q\":\"1\"}",
+            })
+        );
+    }
+}
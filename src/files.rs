@@ -0,0 +1,255 @@
+use proptest::{
+    arbitrary::any,
+    collection::vec,
+    sample::{select, Index},
+    strategy::{BoxedStrategy, Just, Strategy, Union},
+    string::string_regex,
+};
+
+/// Small valid files that uploaded files are mutated from
+const SEEDS: &[Seed] = &[
+    Seed {
+        content: include_bytes!("seeds/seed.png"),
+        content_type: "image/png",
+        extension: "png",
+    },
+    Seed {
+        content: include_bytes!("seeds/seed.zip"),
+        content_type: "application/zip",
+        extension: "zip",
+    },
+    Seed {
+        content: include_bytes!("seeds/seed.pdf"),
+        content_type: "application/pdf",
+        extension: "pdf",
+    },
+];
+
+/// Content types declared for files of a different type
+const MISMATCHED_CONTENT_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/svg+xml",
+    "application/pdf",
+    "application/zip",
+    "application/x-php",
+    "application/x-sh",
+    "text/html",
+    "",
+];
+
+const TRAVERSAL_FILENAMES: &[&str] = &[
+    "../../../../../../etc/passwd",
+    "..\\..\\..\\..\\windows\\win.ini",
+    "/etc/passwd",
+    "....//....//....//etc/passwd",
+    "%2e%2e%2f%2e%2e%2f%2e%2e%2fetc%2fpasswd",
+    "..%c0%af..%c0%afetc%c0%afpasswd",
+    "C:\\Windows\\System32\\drivers\\etc\\hosts",
+    "\\\\localhost\\c$\\boot.ini",
+];
+
+/// Maximum number of bytes inserted into a seed by a single mutation
+const MAX_INSERTED_BYTES: usize = 16;
+
+#[derive(Debug, Clone, Copy)]
+struct Seed {
+    content: &'static [u8],
+    content_type: &'static str,
+    extension: &'static str,
+}
+
+/// File sent as a part of a multipart body
+#[derive(Debug, Clone)]
+pub struct FileUpload {
+    pub filename: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+enum Mutation {
+    Replace(Index, u8),
+    Insert(Index, Vec<u8>),
+    Remove(Index, Index),
+    Truncate(Index),
+}
+
+fn mutation() -> BoxedStrategy<Mutation> {
+    Union::new(vec![
+        (any::<Index>(), any::<u8>())
+            .prop_map(|(index, byte)| Mutation::Replace(index, byte))
+            .boxed(),
+        (any::<Index>(), vec(any::<u8>(), 1..=MAX_INSERTED_BYTES))
+            .prop_map(|(index, bytes)| Mutation::Insert(index, bytes))
+            .boxed(),
+        (any::<Index>(), any::<Index>())
+            .prop_map(|(start, end)| Mutation::Remove(start, end))
+            .boxed(),
+        any::<Index>().prop_map(Mutation::Truncate).boxed(),
+    ])
+    .boxed()
+}
+
+fn mutate(mut bytes: Vec<u8>, mutations: Vec<Mutation>) -> Vec<u8> {
+    for mutation in mutations {
+        match mutation {
+            Mutation::Replace(index, byte) if !bytes.is_empty() => {
+                let index = index.index(bytes.len());
+                bytes[index] = byte;
+            }
+            Mutation::Replace(..) => {}
+            Mutation::Insert(index, inserted) => {
+                let index = index.index(bytes.len() + 1);
+                bytes.splice(index..index, inserted);
+            }
+            Mutation::Remove(start, end) => {
+                let start = start.index(bytes.len() + 1);
+                let end = start + end.index(bytes.len() - start + 1);
+                bytes.drain(start..end);
+            }
+            Mutation::Truncate(index) => bytes.truncate(index.index(bytes.len() + 1)),
+        }
+    }
+    bytes
+}
+
+/// Generates a seed file, unchanged or with a few byte-level mutations.
+fn mutated(seed: Seed) -> BoxedStrategy<Vec<u8>> {
+    Union::new_weighted(vec![
+        (1, Just(seed.content.to_vec()).boxed()),
+        (
+            3,
+            vec(mutation(), 1..=4)
+                .prop_map(move |mutations| mutate(seed.content.to_vec(), mutations))
+                .boxed(),
+        ),
+    ])
+    .boxed()
+}
+
+/// Generates the content of binary bodies from mutated seed files or random bytes.
+pub fn file_content(max_length: usize) -> BoxedStrategy<Vec<u8>> {
+    Union::new_weighted(vec![
+        (3, select(SEEDS).prop_flat_map(mutated).boxed()),
+        (1, vec(any::<u8>(), 0..=max_length).boxed()),
+    ])
+    .boxed()
+}
+
+/// Generates filenames, mostly plain ones with the extension of the file type
+/// but also ones with path traversals, overlong or unusual unicode names.
+fn filename(extension: &'static str) -> BoxedStrategy<String> {
+    let plain = string_regex("[a-zA-Z0-9_-]{1,16}")
+        .expect("valid regex")
+        .prop_map(move |name| format!("{name}.{extension}"))
+        .boxed();
+    Union::new_weighted(vec![
+        (4, plain.clone()),
+        (1, select(TRAVERSAL_FILENAMES).prop_map_into().boxed()),
+        (
+            1,
+            plain
+                .prop_map(|name| format!("../../../../tmp/{name}"))
+                .boxed(),
+        ),
+        (
+            1,
+            (256..=4096usize)
+                .prop_map(move |length| format!("{}.{extension}", "A".repeat(length)))
+                .boxed(),
+        ),
+        (
+            1,
+            any::<String>()
+                .prop_map(move |name| format!("{name}.{extension}"))
+                .boxed(),
+        ),
+        (
+            1,
+            select(vec![
+                format!("\u{202e}gpj.{extension}.exe"),
+                format!("shell.php.{extension}"),
+                format!("shell.php\0.{extension}"),
+                format!(".{extension}"),
+                String::new(),
+            ])
+            .boxed(),
+        ),
+    ])
+    .boxed()
+}
+
+/// Generates an uploaded file for a part whose content type is `declared` in
+/// the specification. The file is sent with the declared type, its actual type
+/// or a type it does not have. `strict` uploads are unchanged seeds of the
+/// declared type, if there is one, with plain names.
+pub fn file_upload(declared: Option<String>, strict: bool) -> BoxedStrategy<FileUpload> {
+    let of_declared_type: Vec<_> = SEEDS
+        .iter()
+        .filter(|seed| declared.as_deref() == Some(seed.content_type))
+        .copied()
+        .collect();
+    let seeds = if strict && !of_declared_type.is_empty() {
+        of_declared_type
+    } else {
+        SEEDS.to_vec()
+    };
+    select(seeds)
+        .prop_flat_map(move |seed| {
+            let declared = declared
+                .clone()
+                .unwrap_or_else(|| seed.content_type.to_owned());
+            if strict {
+                let filename = string_regex("[a-zA-Z0-9_-]{1,16}")
+                    .expect("valid regex")
+                    .prop_map(move |name| format!("{name}.{}", seed.extension));
+                return (filename, Just(declared), Just(seed.content.to_vec())).boxed();
+            }
+            let content_type = Union::new_weighted(vec![
+                (3, Just(declared).boxed()),
+                (1, Just(seed.content_type.to_owned()).boxed()),
+                (1, select(MISMATCHED_CONTENT_TYPES).prop_map_into().boxed()),
+            ]);
+            (
+                filename(seed.extension),
+                content_type.boxed(),
+                mutated(seed),
+            )
+                .boxed()
+        })
+        .prop_map(|(filename, content_type, content)| FileUpload {
+            filename,
+            content_type,
+            content,
+        })
+        .boxed()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::{prop_assert, proptest};
+
+    #[test]
+    fn test_seeds() {
+        assert!(SEEDS[0].content.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert!(SEEDS[1].content.starts_with(b"PK\x03\x04"));
+        assert!(SEEDS[2].content.starts_with(b"%PDF-"));
+    }
+
+    proptest! {
+        #[test]
+        fn test_strict_upload(upload in file_upload(Some("image/png".to_owned()), true)) {
+            prop_assert!(upload.content == SEEDS[0].content);
+            prop_assert!(upload.filename.bytes().all(|b| b.is_ascii_alphanumeric() || b"._-".contains(&b)));
+            prop_assert!(upload.content_type == "image/png");
+        }
+
+        #[test]
+        fn test_mutate(mutations in vec(mutation(), 0..=8)) {
+            let bytes = mutate(b"seed".to_vec(), mutations.clone());
+            prop_assert!(bytes.len() <= 4 + mutations.len() * MAX_INSERTED_BYTES);
+        }
+    }
+}
@@ -0,0 +1,503 @@
+use std::collections::HashMap;
+
+use anyhow::{Error, Result};
+use openapiv3::{StringFormat, VariantOrUnknownOrEmpty};
+use proptest::{
+    arbitrary::any,
+    collection::vec,
+    strategy::{BoxedStrategy, Just, Strategy, Union},
+    string::string_regex,
+};
+
+use crate::arbitrary::{compile_pattern, pattern_violations, Unsupported};
+
+/// Generators of valid values and near misses for a string format
+#[derive(Debug, Clone)]
+pub struct Format {
+    pub valid: BoxedStrategy<String>,
+    pub invalid: Vec<BoxedStrategy<String>>,
+}
+
+impl Format {
+    /// Creates a format whose valid values match the given OpenAPI pattern
+    pub fn from_pattern(pattern: &str) -> Result<Format> {
+        let (valid, matcher) = compile_pattern(pattern, None)
+            .ok_or_else(|| Error::msg(Unsupported::Pattern(pattern.to_owned())))?;
+        Ok(Format {
+            invalid: pattern_violations(&valid, matcher),
+            valid,
+        })
+    }
+}
+
+/// String formats known to the fuzzer, indexed by their name
+#[derive(Debug, Clone)]
+pub struct FormatRegistry(HashMap<String, Format>);
+
+impl FormatRegistry {
+    pub fn register(&mut self, name: impl Into<String>, format: Format) {
+        self.0.insert(name.into(), format);
+    }
+
+    pub fn get(&self, format: &VariantOrUnknownOrEmpty<StringFormat>) -> Option<&Format> {
+        let name = match format {
+            VariantOrUnknownOrEmpty::Item(StringFormat::Date) => "date",
+            VariantOrUnknownOrEmpty::Item(StringFormat::DateTime) => "date-time",
+            VariantOrUnknownOrEmpty::Item(StringFormat::Password) => "password",
+            VariantOrUnknownOrEmpty::Item(StringFormat::Byte) => "byte",
+            VariantOrUnknownOrEmpty::Item(StringFormat::Binary) => "binary",
+            VariantOrUnknownOrEmpty::Unknown(name) => name,
+            VariantOrUnknownOrEmpty::Empty => return None,
+        };
+        self.0.get(name)
+    }
+}
+
+impl Default for FormatRegistry {
+    fn default() -> Self {
+        let mut registry = FormatRegistry(HashMap::new());
+        registry.register("date", date());
+        registry.register("date-time", date_time());
+        registry.register("time", time());
+        registry.register("password", unconstrained());
+        registry.register("binary", unconstrained());
+        registry.register("byte", byte());
+        registry.register("uuid", uuid());
+        registry.register("email", email());
+        registry.register("hostname", hostname());
+        registry.register("uri", uri());
+        registry.register("url", uri());
+        registry.register("ipv4", ipv4());
+        registry.register("ipv6", ipv6());
+        registry
+    }
+}
+
+fn regex(regex: &str) -> BoxedStrategy<String> {
+    string_regex(regex)
+        .expect("built-in format regex is valid")
+        .boxed()
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn valid_date() -> BoxedStrategy<String> {
+    (1900..=2100u32, 1..=12u32, 0..31u32)
+        .prop_map(|(year, month, day)| {
+            let day = day % days_in_month(year, month) + 1;
+            format!("{year:04}-{month:02}-{day:02}")
+        })
+        .boxed()
+}
+
+fn invalid_dates() -> Vec<BoxedStrategy<String>> {
+    vec![
+        (1900..=2100u32)
+            .prop_map(|year| format!("{year:04}-02-30"))
+            .boxed(),
+        (1900..=2100u32, 13..=99u32, 1..=28u32)
+            .prop_map(|(year, month, day)| format!("{year:04}-{month:02}-{day:02}"))
+            .boxed(),
+        (1900..=2100u32, 1..=12u32)
+            .prop_map(|(year, month)| format!("{year:04}-{month:02}-00"))
+            .boxed(),
+        (1900..=2100u32, 1..=9u32, 1..=9u32)
+            .prop_map(|(year, month, day)| format!("{year:04}-{month}-{day}"))
+            .boxed(),
+        (1900..=2100u32, 1..=12u32, 1..=28u32)
+            .prop_map(|(year, month, day)| format!("{year:04}/{month:02}/{day:02}"))
+            .boxed(),
+    ]
+}
+
+fn valid_time() -> BoxedStrategy<String> {
+    (
+        0..24u32,
+        0..60u32,
+        0..60u32,
+        regex("(\\.[0-9]{1,6})?"),
+        regex("Z|[+-](0[0-9]|1[0-4]):[0-5][0-9]"),
+    )
+        .prop_map(|(hour, minute, second, fraction, offset)| {
+            format!("{hour:02}:{minute:02}:{second:02}{fraction}{offset}")
+        })
+        .boxed()
+}
+
+fn invalid_times() -> Vec<BoxedStrategy<String>> {
+    vec![
+        (0..60u32, 0..60u32)
+            .prop_map(|(minute, second)| format!("24:{minute:02}:{second:02}Z"))
+            .boxed(),
+        (0..24u32, 60..100u32)
+            .prop_map(|(hour, minute)| format!("{hour:02}:{minute:02}:00Z"))
+            .boxed(),
+        (0..24u32, 0..60u32, 0..60u32)
+            .prop_map(|(hour, minute, second)| format!("{hour:02}:{minute:02}:{second:02}"))
+            .boxed(),
+    ]
+}
+
+fn date() -> Format {
+    Format {
+        valid: valid_date(),
+        invalid: invalid_dates(),
+    }
+}
+
+fn time() -> Format {
+    Format {
+        valid: valid_time(),
+        invalid: invalid_times(),
+    }
+}
+
+fn date_time() -> Format {
+    let mut invalid: Vec<_> = invalid_dates()
+        .into_iter()
+        .map(|date| {
+            (date, valid_time())
+                .prop_map(|(date, time)| format!("{date}T{time}"))
+                .boxed()
+        })
+        .collect();
+    invalid.extend(invalid_times().into_iter().map(|time| {
+        (valid_date(), time)
+            .prop_map(|(date, time)| format!("{date}T{time}"))
+            .boxed()
+    }));
+    invalid.push(
+        (valid_date(), valid_time())
+            .prop_map(|(date, time)| format!("{date} {time}"))
+            .boxed(),
+    );
+
+    Format {
+        valid: (valid_date(), valid_time())
+            .prop_map(|(date, time)| format!("{date}T{time}"))
+            .boxed(),
+        invalid,
+    }
+}
+
+fn unconstrained() -> Format {
+    Format {
+        valid: any::<String>().boxed(),
+        invalid: vec![],
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn byte() -> Format {
+    let valid = vec(any::<u8>(), 0..64)
+        .prop_map(|bytes| base64(&bytes))
+        .boxed();
+    Format {
+        invalid: vec![
+            // Padding missing or in the middle
+            valid
+                .clone()
+                .prop_map(|s| s.trim_end_matches('=').to_owned() + "A")
+                .boxed(),
+            (valid.clone(), valid.clone())
+                .prop_map(|(a, b)| format!("{a}={b}"))
+                .boxed(),
+            regex("[A-Za-z0-9+/]{0,8}[*.!~][A-Za-z0-9+/]{0,7}"),
+        ],
+        valid,
+    }
+}
+
+fn uuid() -> Format {
+    Format {
+        valid: regex("[0-9a-f]{8}-[0-9a-f]{4}-[1-5][0-9a-f]{3}-[89ab][0-9a-f]{3}-[0-9a-f]{12}"),
+        invalid: vec![
+            // Bad variant
+            regex("[0-9a-f]{8}-[0-9a-f]{4}-[1-5][0-9a-f]{3}-[0-7c-f][0-9a-f]{3}-[0-9a-f]{12}"),
+            regex("[0-9a-f]{32}"),
+            regex("[0-9a-f]{8}-[0-9a-f]{4}-[1-5][0-9a-f]{3}-[89ab][0-9a-f]{3}-[0-9a-f]{11}"),
+            regex("[0-9a-f]{8}-[0-9a-f]{4}-[1-5][0-9a-f]{3}-[89ab][0-9a-f]{3}-[g-z][0-9a-f]{11}"),
+            regex("\\{[0-9a-f]{8}-[0-9a-f]{4}-[1-5][0-9a-f]{3}-[89ab][0-9a-f]{3}-[0-9a-f]{12}\\}"),
+        ],
+    }
+}
+
+const LABEL: &str = "[a-z0-9]([a-z0-9-]{0,14}[a-z0-9])?";
+
+fn email() -> Format {
+    let local = "[a-z0-9]([a-z0-9._+-]{0,14}[a-z0-9])?";
+    Format {
+        valid: regex(&format!("{local}@({LABEL}\\.)+[a-z]{{2,6}}")),
+        invalid: vec![
+            regex(&format!("{local}({LABEL}\\.)+[a-z]{{2,6}}")),
+            regex(&format!("{local}@{local}@{LABEL}\\.[a-z]{{2,6}}")),
+            regex(&format!("{local}@{LABEL}\\.[a-z]{{2,6}}\\.")),
+            regex(&format!("@{LABEL}\\.[a-z]{{2,6}}")),
+            regex(&format!("{local} {local}@{LABEL}\\.[a-z]{{2,6}}")),
+        ],
+    }
+}
+
+fn hostname() -> Format {
+    Format {
+        valid: regex(&format!("({LABEL}\\.){{0,3}}{LABEL}")),
+        invalid: vec![
+            regex(&format!("{LABEL}\\.\\.{LABEL}")),
+            regex(&format!("-{LABEL}")),
+            regex(&format!("{LABEL}_{LABEL}")),
+            regex("[a-z]{64,80}"),
+        ],
+    }
+}
+
+fn uri() -> Format {
+    // Ports of up to four digits are all valid
+    let host = format!("({LABEL}\\.)*{LABEL}(:[0-9]{{1,4}})?");
+    let path = "(/[a-zA-Z0-9._~-]{0,10}){0,4}(\\?[a-z]{1,8}=[a-zA-Z0-9]{0,8})?";
+    Format {
+        valid: regex(&format!("(https?|ftp)://{host}{path}")),
+        invalid: vec![
+            regex(&format!("//{host}{path}")),
+            regex(&format!("https?//{host}{path}")),
+            regex(&format!("https?://{path}")),
+            regex(&format!("https?://{LABEL} {LABEL}{path}")),
+        ],
+    }
+}
+
+fn ipv4() -> Format {
+    Format {
+        valid: any::<[u8; 4]>()
+            .prop_map(|[a, b, c, d]| format!("{a}.{b}.{c}.{d}"))
+            .boxed(),
+        invalid: vec![
+            (256..1000u32, any::<[u8; 3]>())
+                .prop_map(|(a, [b, c, d])| format!("{b}.{a}.{c}.{d}"))
+                .boxed(),
+            any::<[u8; 4]>()
+                .prop_map(|[a, b, c, d]| format!("{a}.{b}.{c}.{d}."))
+                .boxed(),
+            any::<[u8; 3]>()
+                .prop_map(|[a, b, c]| format!("{a}.{b}.{c}"))
+                .boxed(),
+            // At least one octet is below 100, so that padding adds leading zeros
+            (0..100u8, any::<[u8; 3]>(), 0..4usize)
+                .prop_map(|(small, [b, c, d], at)| {
+                    let mut octets = vec![b, c, d];
+                    octets.insert(at, small);
+                    octets
+                        .iter()
+                        .map(|octet| format!("{octet:03}"))
+                        .collect::<Vec<_>>()
+                        .join(".")
+                })
+                .boxed(),
+            any::<[u8; 5]>()
+                .prop_map(|[a, b, c, d, e]| format!("{a}.{b}.{c}.{d}.{e}"))
+                .boxed(),
+        ],
+    }
+}
+
+fn hex_groups(groups: Vec<u16>) -> String {
+    groups
+        .iter()
+        .map(|group| format!("{group:x}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+fn ipv6() -> Format {
+    Format {
+        valid: Union::new(vec![
+            any::<[u16; 8]>()
+                .prop_map(|groups| hex_groups(groups.to_vec()))
+                .boxed(),
+            // Compressed form with `::` replacing at least one zero group
+            (vec(any::<u16>(), 0..=3), vec(any::<u16>(), 0..=3))
+                .prop_map(|(left, right)| format!("{}::{}", hex_groups(left), hex_groups(right)))
+                .boxed(),
+        ])
+        .boxed(),
+        invalid: vec![
+            any::<[u16; 9]>()
+                .prop_map(|groups| hex_groups(groups.to_vec()))
+                .boxed(),
+            any::<[u16; 7]>()
+                .prop_map(|groups| hex_groups(groups.to_vec()))
+                .boxed(),
+            (vec(any::<u16>(), 1..=3), vec(any::<u16>(), 1..=3))
+                .prop_map(|(left, right)| format!("{}:::{}", hex_groups(left), hex_groups(right)))
+                .boxed(),
+            any::<[u16; 8]>()
+                .prop_map(|groups| hex_groups(groups.to_vec()) + ":")
+                .boxed(),
+            (any::<[u16; 7]>(), 0x10000..0xfffffu32)
+                .prop_map(|(groups, big)| format!("{}:{big:x}", hex_groups(groups.to_vec())))
+                .boxed(),
+            Just("::1::".to_owned()).boxed(),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::{prop_assert, prop_assert_eq, proptest};
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    fn is_valid_date(date: &str) -> bool {
+        let parts: Vec<_> = date.split('-').collect();
+        match parts.as_slice() {
+            [year, month, day] if year.len() == 4 && month.len() == 2 && day.len() == 2 => {
+                match (year.parse(), month.parse(), day.parse::<u32>()) {
+                    (Ok(year), Ok(month), Ok(day)) => {
+                        (1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day)
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn is_valid_email(email: &str) -> bool {
+        match email.split_once('@') {
+            Some((local, domain)) => {
+                !local.is_empty()
+                    && domain.contains('.')
+                    && !domain.ends_with('.')
+                    && !domain.contains('@')
+                    && !email.contains(' ')
+            }
+            None => false,
+        }
+    }
+
+    fn is_valid_uri(uri: &str) -> bool {
+        let authority = uri.split_once("://").map(|(_, rest)| rest);
+        url::Url::parse(uri).is_ok()
+            && !uri.contains(' ')
+            && authority.is_some_and(|authority| !authority.starts_with(['/', '?']))
+    }
+
+    proptest! {
+        #[test]
+        fn test_valid_date_time(date_time in date_time().valid) {
+            let (date, time) = date_time.split_once('T').unwrap();
+            let parts: Vec<u32> = date.split('-').map(|part| part.parse().unwrap()).collect();
+            prop_assert!((1..=12).contains(&parts[1]));
+            prop_assert!((1..=days_in_month(parts[0], parts[1])).contains(&parts[2]));
+            prop_assert!(time.ends_with('Z') || time[time.len() - 6..].starts_with(['+', '-']));
+        }
+
+        #[test]
+        fn test_invalid_date(date in Union::new(date().invalid)) {
+            prop_assert!(!is_valid_date(&date));
+        }
+
+        #[test]
+        fn test_valid_ipv4(ip in ipv4().valid) {
+            prop_assert!(ip.parse::<std::net::Ipv4Addr>().is_ok());
+        }
+
+        #[test]
+        fn test_invalid_ipv4(ip in Union::new(ipv4().invalid)) {
+            prop_assert!(ip.parse::<std::net::Ipv4Addr>().is_err());
+        }
+
+        #[test]
+        fn test_valid_ipv6(ip in ipv6().valid) {
+            prop_assert!(ip.parse::<std::net::Ipv6Addr>().is_ok());
+        }
+
+        #[test]
+        fn test_invalid_ipv6(ip in Union::new(ipv6().invalid)) {
+            prop_assert!(ip.parse::<std::net::Ipv6Addr>().is_err());
+        }
+
+        #[test]
+        fn test_valid_uuid(uuid in uuid().valid) {
+            prop_assert_eq!(uuid.len(), 36);
+            prop_assert!(matches!(uuid.as_bytes()[19], b'8' | b'9' | b'a' | b'b'));
+        }
+
+        #[test]
+        fn test_invalid_uuid(uuid in Union::new(uuid().invalid)) {
+            let groups: Vec<_> = uuid.split('-').map(str::len).collect();
+            let is_hex = uuid.chars().all(|c| c == '-' || c.is_ascii_hexdigit());
+            let is_variant = matches!(uuid.as_bytes().get(19), Some(b'8' | b'9' | b'a' | b'b'));
+            prop_assert!(groups != [8, 4, 4, 4, 12] || !is_hex || !is_variant, "{}", uuid);
+        }
+
+        #[test]
+        fn test_valid_email(email in email().valid) {
+            prop_assert!(is_valid_email(&email), "{}", email);
+        }
+
+        #[test]
+        fn test_invalid_email(email in Union::new(email().invalid)) {
+            prop_assert!(!is_valid_email(&email), "{}", email);
+        }
+
+        #[test]
+        fn test_valid_uri(uri in uri().valid) {
+            prop_assert!(is_valid_uri(&uri), "{}", uri);
+        }
+
+        #[test]
+        fn test_invalid_uri(uri in Union::new(uri().invalid)) {
+            prop_assert!(!is_valid_uri(&uri), "{}", uri);
+        }
+
+        #[test]
+        fn test_custom_format_violations(code in Union::new(
+            Format::from_pattern("^[A-Z]{2}-\\d+$").unwrap().invalid
+        )) {
+            let is_code = code
+                .split_once('-')
+                .is_some_and(|(letters, digits)| {
+                    letters.len() == 2
+                        && letters.chars().all(|c| c.is_ascii_uppercase())
+                        && !digits.is_empty()
+                        && digits.chars().all(|c| c.is_ascii_digit())
+                });
+            prop_assert!(!is_code, "{}", code);
+        }
+    }
+}
@@ -1,29 +1,37 @@
 use std::{
     borrow::Cow,
     cell::RefCell,
-    collections::HashMap,
+    collections::{hash_map::RandomState, HashMap},
+    fmt::Display,
     fs::{self, File},
+    hash::{BuildHasher, Hasher},
     mem,
     path::{Path, PathBuf},
     process::ExitCode,
     rc::Rc,
+    str::FromStr,
     time::Instant,
 };
 
 use anyhow::{Context, Error, Result};
-use indexmap::IndexMap;
 use openapi_utils::ReferenceOrExt;
-use openapiv3::{OpenAPI, ReferenceOr, Response, StatusCode};
+use openapiv3::{OpenAPI, Responses, StatusCode};
 use proptest::{
     prelude::any_with,
-    test_runner::{Config, FileFailurePersistence, TestCaseError, TestError, TestRunner},
+    strategy::Strategy,
+    test_runner::{
+        Config, FileFailurePersistence, RngAlgorithm, TestCaseError, TestError, TestRng, TestRunner,
+    },
 };
 use serde::{Deserialize, Serialize};
 use ureq::OrAnyStatus;
 use url::Url;
 
 use crate::{
-    arbitrary::{ArbitraryParameters, Payload},
+    arbitrary::{ArbitraryParameters, GenerationConfig, GenerationContext, Payload},
+    openapi31::KeywordMap,
+    servers::Servers,
+    spec::Resolver,
     stats::Stats,
 };
 
@@ -32,6 +40,82 @@ pub struct FuzzResult<'a> {
     pub payload: Payload,
     pub path: &'a str,
     pub method: &'a str,
+    /// Seed of the run that found the payload
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Url the request was sent to, without the path
+    #[serde(default)]
+    pub url: Option<Url>,
+}
+
+/// Which requests are sent and which responses are expected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Requests mostly follow the specification and any documented response
+    /// other than 5xx is expected
+    Fuzz,
+    /// Every request breaks exactly one constraint and must be rejected with 4xx
+    Negative,
+    /// Every request is valid and must succeed with a documented 2xx
+    Positive,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fuzz" => Ok(Mode::Fuzz),
+            "negative" => Ok(Mode::Negative),
+            "positive" => Ok(Mode::Positive),
+            _ => Err("invalid mode, expected `fuzz`, `negative` or `positive`".to_string()),
+        }
+    }
+}
+
+/// Prints an operation that is not fuzzed with the reasons why.
+fn report_skipped(
+    method: &str,
+    path_with_params: &str,
+    max_path_length: usize,
+    reasons: &[impl Display],
+) {
+    println!(
+        "{method:7} {path_with_params:max_path_length$} {:^7}",
+        "skipped"
+    );
+    for reason in reasons {
+        println!("        {reason:#}");
+    }
+}
+
+/// Whether the status code is documented exactly, by its range such as `2XX`
+/// or by the default response.
+fn is_documented(status: u16, responses: &Responses) -> bool {
+    responses.default.is_some()
+        || responses.responses.contains_key(&StatusCode::Code(status))
+        || responses
+            .responses
+            .contains_key(&StatusCode::Range(status / 100))
+}
+
+/// Derives the random number generator of an operation from the seed of the run,
+/// so that each operation gets the same requests regardless of the others.
+fn operation_rng(seed: u64, method: &str, path: &str) -> TestRng {
+    // FNV-1a, which unlike the standard hasher is stable across Rust versions
+    let operation = format!("{method} {path}")
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+    let mut bytes = [0; 32];
+    bytes[..8].copy_from_slice(&seed.to_le_bytes());
+    bytes[8..16].copy_from_slice(&operation.to_le_bytes());
+    TestRng::from_seed(RngAlgorithm::ChaCha, &bytes)
+}
+
+fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
 }
 
 #[derive(Debug, Default, Serialize)]
@@ -43,32 +127,62 @@ pub struct FuzzStats {
 #[derive(Debug)]
 pub struct Fuzzer {
     schema: OpenAPI,
-    url: Url,
+    keywords: KeywordMap,
+    servers: Servers,
     ignored_status_codes: Vec<u16>,
     extra_headers: HashMap<String, String>,
     max_test_case_count: u32,
     results_dir: PathBuf,
     stats_dir: Option<PathBuf>,
+    generation_config: GenerationConfig,
+    mode: Mode,
+    seed: u64,
+    /// Whether failing cases are saved and replayed, which would make runs with
+    /// the same seed differ
+    persist_failures: bool,
+}
+
+/// How a [`Fuzzer`] sends requests and where it keeps what it finds
+#[derive(Debug)]
+pub struct FuzzerOptions {
+    pub servers: Servers,
+    pub ignored_status_codes: Vec<u16>,
+    pub extra_headers: HashMap<String, String>,
+    pub max_test_case_count: u32,
+    pub results_dir: PathBuf,
+    pub stats_dir: Option<PathBuf>,
+    pub generation_config: GenerationConfig,
+    pub mode: Mode,
+    /// Seed of the run, or `None` for a random seed
+    pub seed: Option<u64>,
 }
 
 impl Fuzzer {
-    pub fn new(
-        schema: OpenAPI,
-        url: Url,
-        ignored_status_codes: Vec<u16>,
-        extra_headers: HashMap<String, String>,
-        max_test_case_count: u32,
-        results_dir: PathBuf,
-        stats_dir: Option<PathBuf>,
-    ) -> Fuzzer {
+    pub fn new(schema: OpenAPI, keywords: KeywordMap, options: FuzzerOptions) -> Fuzzer {
+        let FuzzerOptions {
+            servers,
+            ignored_status_codes,
+            extra_headers,
+            max_test_case_count,
+            results_dir,
+            stats_dir,
+            generation_config,
+            mode,
+            seed,
+        } = options;
         Fuzzer {
             schema,
-            url,
+            keywords,
+            servers,
             ignored_status_codes,
             extra_headers,
             max_test_case_count,
             results_dir,
             stats_dir,
+            generation_config,
+            mode,
+            seed: seed.unwrap_or_else(random_seed),
+            persist_failures: seed.is_none(),
         }
     }
 
@@ -83,23 +197,41 @@ impl Fuzzer {
         };
 
         let config = Config {
-            failure_persistence: Some(Box::new(FileFailurePersistence::Direct(
-                "openapi-fuzzer.regressions",
-            ))),
+            failure_persistence: self.persist_failures.then(|| {
+                Box::new(FileFailurePersistence::Direct("openapi-fuzzer.regressions")) as _
+            }),
             verbose: 0,
             cases: self.max_test_case_count,
             ..Config::default()
         };
         let mut test_failed = false;
         let paths = mem::take(&mut self.schema.paths);
+        let schemas = self
+            .schema
+            .components
+            .as_mut()
+            .map(|components| mem::take(&mut components.schemas))
+            .unwrap_or_default();
+        // Requests of negative mode break only the constraint they are made for and
+        // those of positive mode none
+        let generation_config = match self.mode {
+            Mode::Fuzz => self.generation_config.clone(),
+            Mode::Negative | Mode::Positive => self.generation_config.clone().without_violations(),
+        };
+        let context = GenerationContext::new(
+            generation_config,
+            Rc::new(Resolver::new(schemas, mem::take(&mut self.keywords))),
+        );
         let max_path_length = paths.iter().map(|(path, _)| path.len()).max().unwrap_or(0);
 
+        println!("Seed: {}", self.seed);
         println!("\x1B[1mMETHOD  {path:max_path_length$} STATUS   MEAN (μs) STD.DEV. MIN (μs)   MAX (μs)\x1B[0m",
             path = "PATH"
         );
         for (path_with_params, mut ref_or_item) in paths {
             let path_with_params = path_with_params.trim_start_matches('/');
             let item = ref_or_item.to_item_mut();
+            let path_servers = mem::take(&mut item.servers);
             let operations = vec![
                 ("GET", item.get.take()),
                 ("PUT", item.put.take()),
@@ -115,34 +247,70 @@ impl Fuzzer {
                 .into_iter()
                 .filter_map(|(method, operation)| operation.map(|operation| (method, operation)))
             {
-                let responses = mem::take(&mut operation.responses.responses);
+                let responses = mem::take(&mut operation.responses);
+                let url =
+                    match self
+                        .servers
+                        .url(&operation.servers, &path_servers, &self.schema.servers)
+                    {
+                        Ok(url) => url,
+                        Err(e) => {
+                            report_skipped(method, path_with_params, max_path_length, &[e]);
+                            continue;
+                        }
+                    };
+                let args = ArbitraryParameters::new(operation, context.clone());
+                let unsupported = args.unsupported();
+                // Positive mode sends only valid requests
+                if unsupported
+                    .iter()
+                    .any(|unsupported| self.mode == Mode::Positive || !unsupported.is_ignorable())
+                {
+                    report_skipped(method, path_with_params, max_path_length, &unsupported);
+                    continue;
+                }
+                for unsupported in &unsupported {
+                    eprintln!("{method} {path_with_params}: {unsupported}, ignoring it");
+                }
+                let strategy = match self.mode {
+                    Mode::Fuzz | Mode::Positive => any_with::<Payload>(Rc::new(args)).boxed(),
+                    Mode::Negative => match Payload::negative(&args) {
+                        Some(strategy) => strategy,
+                        None => {
+                            let reasons = ["nothing in the operation can be invalid"];
+                            report_skipped(method, path_with_params, max_path_length, &reasons);
+                            continue;
+                        }
+                    },
+                };
 
                 let stats = RefCell::new(FuzzStats::default());
 
-                let result = TestRunner::new(config.clone()).run(
-                    &any_with::<Payload>(Rc::new(ArbitraryParameters::new(operation))),
-                    |payload| {
-                        let now = Instant::now();
-                        let response = Fuzzer::send_request(
-                            &self.url,
-                            path_with_params.to_owned(),
-                            method,
-                            &payload,
-                            &self.extra_headers,
-                        )
-                        .map_err(|e| {
-                            TestCaseError::Fail(format!("unable to send request: {e}").into())
-                        })?;
-
-                        let is_expected_response = self.is_expected_response(&response, &responses);
-                        stats.borrow_mut().times.push(now.elapsed().as_micros());
-                        stats.borrow_mut().did_failed.push(!is_expected_response);
-
-                        is_expected_response
-                            .then_some(())
-                            .ok_or(TestCaseError::Fail(response.status().to_string().into()))
-                    },
-                );
+                let result = TestRunner::new_with_rng(
+                    config.clone(),
+                    operation_rng(self.seed, method, path_with_params),
+                )
+                .run(&strategy, |payload| {
+                    let now = Instant::now();
+                    let response = Fuzzer::send_request(
+                        &url,
+                        path_with_params.to_owned(),
+                        method,
+                        &payload,
+                        &self.extra_headers,
+                    )
+                    .map_err(|e| {
+                        TestCaseError::Fail(format!("unable to send request: {e}").into())
+                    })?;
+
+                    let is_expected_response = self.is_expected_response(&response, &responses);
+                    stats.borrow_mut().times.push(now.elapsed().as_micros());
+                    stats.borrow_mut().did_failed.push(!is_expected_response);
+
+                    is_expected_response
+                        .then_some(())
+                        .ok_or(TestCaseError::Fail(response.status().to_string().into()))
+                });
                 let stats = stats.into_inner();
                 if let Some(dir) = &self.stats_dir {
                     Fuzzer::save_stats(dir, path_with_params, method, &stats)?;
@@ -150,6 +318,7 @@ impl Fuzzer {
 
                 test_failed |= result.is_err();
                 self.report_run(
+                    &url,
                     method,
                     path_with_params,
                     result,
@@ -188,6 +357,25 @@ impl Fuzzer {
             request = request.set(header, value);
         }
 
+        // Add generated cookies to the ones from command line, which take precedence
+        let extra_cookies = extra_headers
+            .get("cookie")
+            .map(|cookies| cookies.trim().trim_end_matches(';'));
+        let mut cookies: Vec<_> = extra_cookies.into_iter().map(str::to_owned).collect();
+        for (name, value) in payload.cookies() {
+            let overridden = extra_cookies.is_some_and(|extra_cookies| {
+                extra_cookies
+                    .split(';')
+                    .any(|cookie| cookie.trim().split('=').next() == Some(name.as_str()))
+            });
+            if !overridden {
+                cookies.push(format!("{name}={value}"));
+            }
+        }
+        if !cookies.is_empty() {
+            request = request.set("Cookie", &cookies.join("; "));
+        }
+
         // Add remaining extra headers
         for (header, value) in extra_headers.iter() {
             if request.header(header).is_none() {
@@ -196,26 +384,35 @@ impl Fuzzer {
         }
 
         match payload.body() {
-            Some(json) => request.send_json(json.clone()),
+            Some(body) => {
+                if request.header("Content-Type").is_none() {
+                    request = request.set("Content-Type", &body.content_type_header());
+                }
+                request.send_bytes(&body.to_bytes())
+            }
             None => request.call(),
         }
         .or_any_status()
         .map_err(Into::into)
     }
 
-    fn is_expected_response(
-        &self,
-        resp: &ureq::Response,
-        responses: &IndexMap<StatusCode, ReferenceOr<Response>>,
-    ) -> bool {
-        // known non 500 and ingored status codes are OK
-        self.ignored_status_codes.contains(&resp.status())
-            || (responses.contains_key(&StatusCode::Code(resp.status()))
-                && resp.status() / 100 != 5)
+    fn is_expected_response(&self, resp: &ureq::Response, responses: &Responses) -> bool {
+        if self.ignored_status_codes.contains(&resp.status()) {
+            return true;
+        }
+        match self.mode {
+            // known non 500 status codes are OK
+            Mode::Fuzz => is_documented(resp.status(), responses) && resp.status() / 100 != 5,
+            // invalid requests must be rejected by the server
+            Mode::Negative => resp.status() / 100 == 4,
+            // valid requests must succeed as documented
+            Mode::Positive => is_documented(resp.status(), responses) && resp.status() / 100 == 2,
+        }
     }
 
     fn save_finding(
         &self,
+        url: &Url,
         path: &str,
         method: &str,
         payload: Payload,
@@ -232,6 +429,8 @@ impl Fuzzer {
                 payload,
                 path,
                 method,
+                seed: Some(self.seed),
+                url: Some(url.clone()),
             },
         )
         .map_err(Into::into)
@@ -249,12 +448,16 @@ impl Fuzzer {
 
     fn report_run(
         &self,
+        url: &Url,
         method: &str,
         path_with_params: &str,
         result: Result<(), TestError<Payload>>,
         max_path_length: usize,
         times: &[u128],
     ) -> Result<()> {
+        let mut violation = None;
+        let mut malformation = None;
+        let mut attacks = vec![];
         let status = match result {
             Err(TestError::Fail(reason, payload)) => {
                 let reason: Cow<str> = reason.message().into();
@@ -262,7 +465,12 @@ impl Fuzzer {
                     .parse::<u16>()
                     .map_err(|_| Error::msg(reason.into_owned()))?;
 
-                self.save_finding(path_with_params, method, payload, status_code)?;
+                violation = payload
+                    .violation()
+                    .map(|violation| (status_code, violation.to_owned()));
+                attacks = payload.attacks().to_vec();
+                malformation = payload.malformation().map(str::to_owned);
+                self.save_finding(url, path_with_params, method, payload, status_code)?;
                 "failed"
             }
             Ok(()) => "ok",
@@ -276,6 +484,92 @@ impl Fuzzer {
             std_dev,
         } = Stats::compute(times).ok_or(Error::msg("no requests sent"))?;
         println!("{method:7} {path_with_params:max_path_length$} {status:^7} {mean:10.0} {std_dev:8.0} {min:8} {max:10}");
+        if let Some((status_code, violation)) = violation {
+            // Negative mode also fails on server errors, which are not acceptance
+            let outcome = if (500..600).contains(&status_code) {
+                "crashed on"
+            } else {
+                "accepted"
+            };
+            println!("        {outcome} a request breaking {violation}");
+        }
+        if !attacks.is_empty() {
+            println!("        sent attack payloads: {}", attacks.join(", "));
+        }
+        if let Some(malformation) = malformation {
+            println!("        sent a malformed body: {malformation}");
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::prelude::RngCore;
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    /// Sends the payload to a server that answers once and returns the values of
+    /// the `Cookie` headers it received.
+    fn received_cookies(payload: &Payload, extra_headers: &HashMap<String, String>) -> Vec<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut cookies = vec![];
+            for line in BufReader::new(&stream).lines() {
+                let line = line.unwrap();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(": ") {
+                    if name.eq_ignore_ascii_case("cookie") {
+                        cookies.push(value.to_owned());
+                    }
+                }
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            cookies
+        });
+        Fuzzer::send_request(&url, "pets".to_owned(), "GET", payload, extra_headers).unwrap();
+        server.join().unwrap()
+    }
+
+    #[test]
+    fn test_send_cookies() {
+        let payload: Payload = serde_json::from_value(serde_json::json!({
+            "query_params": [],
+            "path_params": [],
+            "headers": [],
+            "cookies": [["session", "abc"], ["theme", "dark"]],
+            "body": null,
+        }))
+        .unwrap();
+        assert_eq!(
+            received_cookies(&payload, &HashMap::new()),
+            ["session=abc; theme=dark"]
+        );
+
+        let mut extra_headers = HashMap::new();
+        extra_headers.insert("cookie".to_owned(), "session=xyz; lang=en;".to_owned());
+        assert_eq!(
+            received_cookies(&payload, &extra_headers),
+            ["session=xyz; lang=en; theme=dark"]
+        );
+    }
+
+    #[test]
+    fn test_operation_rng() {
+        let next = |seed, method, path| operation_rng(seed, method, path).next_u64();
+        assert_eq!(next(42, "GET", "pets"), next(42, "GET", "pets"));
+        assert_ne!(next(42, "GET", "pets"), next(42, "POST", "pets"));
+        assert_ne!(next(42, "GET", "pets"), next(42, "GET", "pets/{id}"));
+        assert_ne!(next(42, "GET", "pets"), next(43, "GET", "pets"));
+    }
+}
@@ -1,6 +1,19 @@
 mod arbitrary;
+mod body;
+mod bundle;
+mod dictionary;
+mod files;
+mod formats;
 mod fuzzer;
+mod malformed;
+mod merge;
+mod mutate;
+mod openapi31;
+mod servers;
+mod spec;
 mod stats;
+mod style;
+mod swagger;
 
 use std::path::PathBuf;
 use std::process::ExitCode;
@@ -9,12 +22,16 @@ use std::{fs, time::Instant};
 
 use anyhow::{Context, Result};
 use argh::FromArgs;
-use fuzzer::Fuzzer;
-use openapi_utils::SpecExt;
-use openapiv3::OpenAPI;
+use fuzzer::{Fuzzer, FuzzerOptions};
 use url::{ParseError, Url};
 
-use crate::fuzzer::FuzzResult;
+use crate::{
+    arbitrary::GenerationConfig,
+    dictionary::Dictionary,
+    formats::{Format, FormatRegistry},
+    fuzzer::{FuzzResult, Mode},
+    servers::Servers,
+};
 
 #[derive(FromArgs, PartialEq, Debug)]
 /// openapi-fuzzer - black-box fuzzer that fuzzes APIs based on OpenAPI Specification
@@ -26,7 +43,7 @@ struct Cli {
 #[derive(FromArgs, PartialEq, Debug)]
 #[argh(subcommand)]
 enum Subcommands {
-    Run(RunArgs),
+    Run(Box<RunArgs>),
     Resend(ResendArgs),
 }
 
@@ -34,13 +51,24 @@ enum Subcommands {
 /// run openapi-fuzzer
 #[argh(subcommand, name = "run")]
 struct RunArgs {
-    /// path to OpenAPI specification file
+    /// path to OpenAPI 3.0 or 3.1 or Swagger 2.0 specification file
     #[argh(option, short = 's')]
     spec: PathBuf,
 
-    /// url of api to fuzz
+    /// url of api to fuzz, which replaces the servers of the specification. if no
+    /// url is given, requests are sent to the servers of the specification
     #[argh(option, short = 'u')]
-    url: UrlWithTrailingSlash,
+    url: Option<UrlWithTrailingSlash>,
+
+    /// value of a variable of the server urls in form of `name=value`, e.g.
+    /// `region=eu`. variables that are not given use their default
+    #[argh(option)]
+    server_var: Vec<ServerVar>,
+
+    /// index of the server to send requests to when the specification lists
+    /// several (default: 0)
+    #[argh(option, default = "0")]
+    server_index: usize,
 
     /// status codes that will not be considered as finding
     #[argh(option, short = 'i')]
@@ -64,8 +92,84 @@ struct RunArgs {
     /// will not be saved
     #[argh(option)]
     stats_dir: Option<PathBuf>,
+
+    /// how requests are generated and checked: `fuzz` sends mostly valid requests
+    /// and expects documented non-5xx responses, `negative` breaks exactly one
+    /// constraint per request and expects 4xx, `positive` sends only valid
+    /// requests and expects documented 2xx (default: fuzz)
+    #[argh(option, default = "Mode::Fuzz")]
+    mode: Mode,
+
+    /// seed of the random number generator, which makes runs reproducible. a
+    /// random seed is used if none is given. seeded runs do not use the
+    /// regressions file
+    #[argh(option)]
+    seed: Option<u64>,
+
+    /// probability of generating a value that violates a constraint of its schema,
+    /// e.g. a string longer than maxLength (default: 0.1)
+    #[argh(option, default = "0.1")]
+    violation_rate: f64,
+
+    /// probability of sending an optional property or parameter (default: 0.5)
+    #[argh(option, default = "0.5")]
+    optional_rate: f64,
+
+    /// probability of sending null for a nullable value (default: 0.1)
+    #[argh(option, default = "0.1")]
+    null_rate: f64,
+
+    /// number of nested references to follow before generating values as small as
+    /// their schema allows, which ends recursive schemas (default: 4)
+    #[argh(option, default = "4")]
+    max_depth: usize,
+
+    /// maximum number of items of generated arrays without `maxItems` (default: 10)
+    #[argh(option, default = "10")]
+    max_size: usize,
+
+    /// probability of sending a path, query or header parameter as an arbitrary
+    /// string that ignores its schema (default: 0)
+    #[argh(option, default = "0.")]
+    type_violation_rate: f64,
+
+    /// probability of sending an example or default value from the specification,
+    /// unchanged or mutated, instead of a generated one (default: 0.2)
+    #[argh(option, default = "0.2")]
+    example_rate: f64,
+
+    /// probability of replacing a generated string with a payload from the attack
+    /// dictionary, e.g. an SQL injection (default: 0.05)
+    #[argh(option, default = "0.05")]
+    attack_rate: f64,
+
+    /// probability of sending a malformed request body, e.g. truncated JSON,
+    /// invalid UTF-8 or a mismatched content type (default: 0.05)
+    #[argh(option, default = "0.05")]
+    malformed_rate: f64,
+
+    /// file with additional attack payloads, one per line after the `[name]` of
+    /// their class
+    #[argh(option)]
+    dictionary: Vec<PathBuf>,
+
+    /// weight of an attack class in form of `name=weight`, e.g.
+    /// `sql-injection=5`. classes have a weight of 1 by default and 0 disables a
+    /// class
+    #[argh(option)]
+    attack_weight: Vec<AttackWeight>,
+
+    /// send read-only properties in request bodies to detect mass assignment
+    #[argh(switch)]
+    inject_read_only: bool,
+
+    /// custom string format in form of `name=pattern`, e.g. `x-phone=^\+[0-9]+$`.
+    /// values of strings with this format will be generated from the pattern
+    #[argh(option)]
+    format: Vec<CustomFormat>,
 }
 
+// The arguments of `run` are boxed, as they are much larger than those of `resend`
 #[derive(FromArgs, Debug, PartialEq)]
 /// resend payload genereted by fuzzer
 #[argh(subcommand, name = "resend")]
@@ -78,9 +182,10 @@ struct ResendArgs {
     #[argh(option, short = 'H')]
     header: Vec<Header>,
 
-    /// url of api
+    /// url of api. if no url is given, the request is sent to the url saved in
+    /// the result file
     #[argh(option, short = 'u')]
-    url: UrlWithTrailingSlash,
+    url: Option<UrlWithTrailingSlash>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -107,6 +212,68 @@ impl From<Header> for (String, String) {
     }
 }
 
+#[derive(Debug, PartialEq)]
+struct CustomFormat {
+    name: String,
+    pattern: String,
+}
+
+impl FromStr for CustomFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((name, pattern)) if !name.is_empty() => Ok(CustomFormat {
+                name: name.to_string(),
+                pattern: pattern.to_string(),
+            }),
+            _ => Err("invalid format, expected `name=pattern`".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct AttackWeight {
+    class: String,
+    weight: u32,
+}
+
+impl FromStr for AttackWeight {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((class, weight)) if !class.is_empty() => Ok(AttackWeight {
+                class: class.to_string(),
+                weight: weight
+                    .parse()
+                    .map_err(|_| "invalid weight, expected a whole number".to_string())?,
+            }),
+            _ => Err("invalid attack weight, expected `name=weight`".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct ServerVar {
+    name: String,
+    value: String,
+}
+
+impl FromStr for ServerVar {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((name, value)) if !name.is_empty() => Ok(ServerVar {
+                name: name.to_string(),
+                value: value.to_string(),
+            }),
+            _ => Err("invalid server variable, expected `name=value`".to_string()),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 struct UrlWithTrailingSlash(Url);
 
@@ -133,21 +300,56 @@ fn main() -> Result<ExitCode> {
 
     let exit_code = match args.subcommands {
         Subcommands::Run(args) => {
-            let specfile = std::fs::read_to_string(&args.spec)
-                .context(format!("Unable to read {:?}", &args.spec))?;
-            let openapi_schema: OpenAPI =
-                serde_yaml::from_str(&specfile).context("Failed to parse schema")?;
-            let openapi_schema = openapi_schema.deref_all();
+            let (openapi_schema, keywords) = spec::load(&args.spec)?;
+
+            let mut formats = FormatRegistry::default();
+            for CustomFormat { name, pattern } in args.format {
+                formats.register(name, Format::from_pattern(&pattern)?);
+            }
+            let mut dictionary = Dictionary::default();
+            for path in &args.dictionary {
+                dictionary.load(path)?;
+            }
+            for AttackWeight { class, weight } in args.attack_weight {
+                dictionary.set_weight(&class, weight)?;
+            }
 
             let now = Instant::now();
             let exit_code = Fuzzer::new(
                 openapi_schema,
-                args.url.into(),
-                args.ignore_status_code,
-                args.header.into_iter().map(Into::into).collect(),
-                args.max_test_case_count,
-                args.results_dir,
-                args.stats_dir,
+                keywords,
+                FuzzerOptions {
+                    servers: Servers::new(
+                        args.url.map(Into::into),
+                        args.server_index,
+                        args.server_var
+                            .into_iter()
+                            .map(|ServerVar { name, value }| (name, value))
+                            .collect(),
+                    ),
+                    ignored_status_codes: args.ignore_status_code,
+                    extra_headers: args.header.into_iter().map(Into::into).collect(),
+                    max_test_case_count: args.max_test_case_count,
+                    results_dir: args.results_dir,
+                    stats_dir: args.stats_dir,
+                    generation_config: GenerationConfig {
+                        violation_rate: args.violation_rate,
+                        optional_rate: args.optional_rate,
+                        null_rate: args.null_rate,
+                        type_violation_rate: args.type_violation_rate,
+                        example_rate: args.example_rate,
+                        attack_rate: args.attack_rate,
+                        malformed_rate: args.malformed_rate,
+                        inject_read_only: args.inject_read_only,
+                        strict_files: false,
+                        max_depth: args.max_depth,
+                        max_size: args.max_size,
+                        formats,
+                        dictionary,
+                    },
+                    mode: args.mode,
+                    seed: args.seed,
+                },
             )
             .run()?;
             println!("Elapsed time: {}s", now.elapsed().as_secs());
@@ -157,8 +359,15 @@ fn main() -> Result<ExitCode> {
             let json = fs::read_to_string(&args.file)
                 .context(format!("Unable to read {:?}", &args.file))?;
             let result: FuzzResult = serde_json::from_str(&json)?;
+            let url = match args.url {
+                Some(url) => url.into(),
+                None => result
+                    .url
+                    .clone()
+                    .context("The result file has no url, pass the url of the api with -u")?,
+            };
             let response = Fuzzer::send_request(
-                &args.url.into(),
+                &url,
                 result.path.to_owned(),
                 result.method,
                 &result.payload,
@@ -0,0 +1,238 @@
+use proptest::{
+    arbitrary::any,
+    sample::{select, Index},
+    strategy::{BoxedStrategy, Just, Strategy, Union},
+};
+use serde_json::Value;
+
+use crate::{
+    body::{Body, Content, Malformed},
+    mutate::pointers,
+};
+
+/// Range of the number of nested arrays sent as a JSON bomb
+const NESTING: std::ops::RangeInclusive<usize> = 1_000..=100_000;
+
+/// Numbers that overflow, underflow or are not valid JSON at all
+const NUMBER_LITERALS: &[&str] = &[
+    "1e400",
+    "-1e400",
+    "1e-400",
+    "-0",
+    "-0.0",
+    "123456789012345678901234567890",
+    "0.1e99999",
+    "NaN",
+    "Infinity",
+    "01",
+    "+1",
+    ".5",
+];
+
+/// Byte sequences that are not valid UTF-8
+const INVALID_UTF8: &[&[u8]] = &[
+    b"\xff",
+    b"\xc3\x28",
+    b"\xe2\x28\xa1",
+    b"\xf0\x90\x28\xbc",
+    b"\xc0\xaf",
+    b"\xed\xa0\x80",
+];
+
+/// Byte order marks of UTF-8, UTF-16 and UTF-32
+const BOMS: &[&[u8]] = &[
+    b"\xef\xbb\xbf",
+    b"\xfe\xff",
+    b"\xff\xfe",
+    b"\x00\x00\xfe\xff",
+];
+
+const CONTENT_TYPES: &[&str] = &[
+    "application/json",
+    "application/xml",
+    "text/plain",
+    "application/x-www-form-urlencoded",
+    "multipart/form-data",
+    "application/octet-stream",
+];
+
+/// Replaces the number picked for the `number-literal` mutation before the
+/// value is serialized
+const PLACEHOLDER: &str = "openapi-fuzzer-number-literal";
+
+type Mutation = (&'static str, BoxedStrategy<Malformed>);
+
+/// Sends the bytes instead of the body
+fn replaced(bytes: Vec<u8>) -> Malformed {
+    Malformed {
+        mutation: String::new(),
+        bytes,
+        content_type: None,
+        nesting: None,
+    }
+}
+
+/// Generates variations of a body whose bytes are no longer well-formed, e.g.
+/// truncated JSON or invalid UTF-8, or which are sent with a content type that
+/// disagrees with them.
+pub fn malformed(body: Body) -> BoxedStrategy<Body> {
+    let bytes = body.to_bytes();
+    let mut mutations: Vec<Mutation> = vec![
+        ("invalid-utf8", {
+            let bytes = bytes.clone();
+            (any::<Index>(), select(INVALID_UTF8))
+                .prop_map(move |(index, sequence)| {
+                    let mut bytes = bytes.clone();
+                    let at = index.index(bytes.len() + 1);
+                    bytes.splice(at..at, sequence.iter().copied());
+                    replaced(bytes)
+                })
+                .boxed()
+        }),
+        ("bom", {
+            let bytes = bytes.clone();
+            select(BOMS)
+                .prop_map(move |bom| replaced([bom, &bytes[..]].concat()))
+                .boxed()
+        }),
+    ];
+    if !bytes.is_empty() {
+        let truncated = bytes.clone();
+        mutations.push((
+            "truncated",
+            any::<Index>()
+                .prop_map(move |index| replaced(truncated[..index.index(truncated.len())].to_vec()))
+                .boxed(),
+        ));
+    }
+    let essence = body
+        .content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim();
+    let content_types: Vec<_> = CONTENT_TYPES
+        .iter()
+        .filter(|content_type| !essence.eq_ignore_ascii_case(content_type))
+        .map(|content_type| content_type.to_string())
+        .collect();
+    mutations.push((
+        "content-type",
+        select(content_types)
+            .prop_map(move |content_type| Malformed {
+                content_type: Some(content_type),
+                ..replaced(bytes.clone())
+            })
+            .boxed(),
+    ));
+    if let Content::Json(value) = &body.content {
+        mutations.extend(json_mutations(value));
+    }
+
+    Union::new(mutations.into_iter().map(move |(mutation, strategy)| {
+        let body = body.clone();
+        strategy.prop_map(move |malformed| Body {
+            malformed: Some(Malformed {
+                mutation: mutation.to_owned(),
+                ..malformed
+            }),
+            ..body.clone()
+        })
+    }))
+    .boxed()
+}
+
+/// Mutations that keep the body almost valid JSON
+fn json_mutations(value: &Value) -> Vec<Mutation> {
+    let text = value.to_string();
+    let mut mutations: Vec<Mutation> = vec![(
+        "nested-arrays",
+        NESTING
+            .prop_map(|depth| Malformed {
+                nesting: Some(depth),
+                ..replaced(vec![])
+            })
+            .boxed(),
+    )];
+    if let Value::Object(object) = value {
+        if let Some((name, _)) = object.iter().next() {
+            let prefix = format!(
+                "{},{}:",
+                &text[..text.len() - 1],
+                Value::from(name.as_str())
+            );
+            mutations.push((
+                "duplicate-key",
+                select(vec!["null", "0", "\"duplicate\"", "{}"])
+                    .prop_map(move |duplicate| {
+                        replaced(format!("{prefix}{duplicate}}}").into_bytes())
+                    })
+                    .boxed(),
+            ));
+        }
+    }
+    if value.is_object() || value.is_array() {
+        let (start, end) = text.split_at(text.len() - 1);
+        mutations.push((
+            "trailing-comma",
+            Just(replaced(format!("{start},{end}").into_bytes())).boxed(),
+        ));
+    }
+
+    let mut all = vec![];
+    pointers(value, String::new(), &mut all);
+    let numbers: Vec<_> = all
+        .iter()
+        .filter(|pointer| value.pointer(pointer).is_some_and(Value::is_number))
+        .cloned()
+        .collect();
+    let value = value.clone();
+    mutations.push((
+        "number-literal",
+        (
+            select(if numbers.is_empty() { all } else { numbers }),
+            select(NUMBER_LITERALS),
+        )
+            .prop_map(move |(pointer, literal)| {
+                let mut value = value.clone();
+                if let Some(target) = value.pointer_mut(&pointer) {
+                    *target = Value::from(PLACEHOLDER);
+                }
+                let text = value
+                    .to_string()
+                    .replace(&format!("\"{PLACEHOLDER}\""), literal);
+                replaced(text.into_bytes())
+            })
+            .boxed(),
+    ));
+    mutations
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::{prop_assert, prop_assert_eq, prop_assert_ne, proptest};
+    use serde_json::json;
+
+    fn json_body() -> Body {
+        Body {
+            content_type: "application/json".to_owned(),
+            content: Content::Json(json!({ "name": "Rex", "age": 3 })),
+            malformed: None,
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_malformed(body in malformed(json_body())) {
+            let malformed = body.malformed.as_ref().unwrap();
+            let valid = serde_json::from_slice::<Value>(&body.to_bytes())
+                .is_ok_and(|value| value == json!({ "name": "Rex", "age": 3 }));
+            match malformed.content_type.as_deref() {
+                Some(content_type) => prop_assert_ne!(content_type, "application/json"),
+                None => prop_assert!(!valid, "{} sent valid JSON", malformed.mutation),
+            }
+            prop_assert_eq!(&body.content_type_header(), malformed.content_type.as_ref().unwrap_or(&body.content_type));
+        }
+    }
+}
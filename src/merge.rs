@@ -0,0 +1,636 @@
+use std::{borrow::Borrow, cell::RefCell};
+
+use anyhow::{bail, Context, Result};
+use openapiv3::{
+    AdditionalProperties, AnySchema, ArrayType, IntegerFormat, IntegerType, NumberType, ObjectType,
+    ReferenceOr, Schema, SchemaData, SchemaKind, StringType, Type, VariantOrUnknownOrEmpty,
+};
+
+use crate::spec::Resolver;
+
+/// Merges the schemas of `allOf` into a single schema that satisfies all of them.
+/// `oneOf` and `anyOf` inside `allOf` are distributed, so that each of their
+/// alternatives is merged with the remaining schemas. Referenced schemas are
+/// looked up with `resolver`.
+pub fn merge_all_of(schemas: &[ReferenceOr<Schema>], resolver: &Resolver) -> Result<Schema> {
+    let merging = Merging {
+        resolver,
+        references: RefCell::default(),
+    };
+    merge_all_of_schemas(schemas, &merging)
+}
+
+/// Looks up referenced schemas and keeps track of the references whose schemas
+/// are being merged
+struct Merging<'a> {
+    resolver: &'a Resolver,
+    references: RefCell<Vec<String>>,
+}
+
+impl Merging<'_> {
+    /// Merges the schema itself or the one it references with `merge`. Fails if
+    /// the reference is already being merged, as merging it again would never end.
+    fn resolved<T: Borrow<Schema>, R>(
+        &self,
+        ref_or_schema: &ReferenceOr<T>,
+        merge: impl FnOnce(&Schema) -> Result<R>,
+    ) -> Result<R> {
+        let reference = match ref_or_schema {
+            ReferenceOr::Item(schema) => return merge(schema.borrow()),
+            ReferenceOr::Reference { reference } => reference,
+        };
+        if self.references.borrow().contains(reference) {
+            bail!("cyclic reference `{}`", reference);
+        }
+        let schema = self.resolver.schema(reference)?;
+        self.references.borrow_mut().push(reference.clone());
+        let merged = merge(schema);
+        self.references.borrow_mut().pop();
+        merged
+    }
+}
+
+fn merge_all_of_schemas(schemas: &[ReferenceOr<Schema>], merging: &Merging) -> Result<Schema> {
+    let mut merged = Schema {
+        schema_data: SchemaData::default(),
+        schema_kind: SchemaKind::Any(AnySchema::default()),
+    };
+    for (i, schema) in schemas.iter().enumerate() {
+        merged = merging
+            .resolved(schema, |schema| merge_schemas(&merged, schema, merging))
+            .context(format!("in allOf schema #{i}"))?;
+    }
+    Ok(merged)
+}
+
+fn merge_schemas(a: &Schema, b: &Schema, merging: &Merging) -> Result<Schema> {
+    Ok(Schema {
+        schema_data: merge_schema_data(&a.schema_data, &b.schema_data),
+        schema_kind: merge_schema_kinds(&a.schema_kind, &b.schema_kind, merging)?,
+    })
+}
+
+fn merge_schema_data(a: &SchemaData, b: &SchemaData) -> SchemaData {
+    SchemaData {
+        nullable: a.nullable && b.nullable,
+        read_only: a.read_only || b.read_only,
+        write_only: a.write_only || b.write_only,
+        deprecated: a.deprecated || b.deprecated,
+        external_docs: a.external_docs.clone().or_else(|| b.external_docs.clone()),
+        example: a.example.clone().or_else(|| b.example.clone()),
+        title: a.title.clone().or_else(|| b.title.clone()),
+        description: a.description.clone().or_else(|| b.description.clone()),
+        discriminator: a.discriminator.clone().or_else(|| b.discriminator.clone()),
+        default: a.default.clone().or_else(|| b.default.clone()),
+    }
+}
+
+fn merge_schema_kinds(a: &SchemaKind, b: &SchemaKind, merging: &Merging) -> Result<SchemaKind> {
+    match (a, b) {
+        (SchemaKind::AllOf { all_of }, other) | (other, SchemaKind::AllOf { all_of }) => {
+            let merged = merge_all_of_schemas(all_of, merging)?;
+            merge_schema_kinds(&merged.schema_kind, other, merging)
+        }
+        (SchemaKind::OneOf { one_of: schemas }, other)
+        | (other, SchemaKind::OneOf { one_of: schemas })
+        | (SchemaKind::AnyOf { any_of: schemas }, other)
+        | (other, SchemaKind::AnyOf { any_of: schemas }) => {
+            let mut alternatives = vec![];
+            let mut last_error = None;
+            for schema in schemas {
+                let merged = merging.resolved(schema, |schema| {
+                    Ok(Schema {
+                        schema_data: schema.schema_data.clone(),
+                        schema_kind: merge_schema_kinds(&schema.schema_kind, other, merging)?,
+                    })
+                });
+                match merged {
+                    Ok(schema) => alternatives.push(ReferenceOr::Item(schema)),
+                    Err(e) => last_error = Some(e),
+                }
+            }
+            match last_error {
+                Some(e) if alternatives.is_empty() => {
+                    Err(e.context("no alternative of oneOf/anyOf can be merged"))
+                }
+                _ => Ok(SchemaKind::AnyOf {
+                    any_of: alternatives,
+                }),
+            }
+        }
+        (SchemaKind::Any(a), SchemaKind::Any(b)) => match (any_to_type(a), any_to_type(b)) {
+            (Some(a), Some(b)) => merge_types(&a, &b, merging).map(SchemaKind::Type),
+            (Some(schema_type), None) | (None, Some(schema_type)) => {
+                Ok(SchemaKind::Type(schema_type))
+            }
+            (None, None) => Ok(SchemaKind::Any(a.clone())),
+        },
+        (SchemaKind::Any(any), SchemaKind::Type(schema_type))
+        | (SchemaKind::Type(schema_type), SchemaKind::Any(any)) => match any_to_type(any) {
+            Some(any_type) => merge_types(&any_type, schema_type, merging).map(SchemaKind::Type),
+            None => Ok(SchemaKind::Type(schema_type.clone())),
+        },
+        (SchemaKind::Type(a), SchemaKind::Type(b)) => {
+            merge_types(a, b, merging).map(SchemaKind::Type)
+        }
+    }
+}
+
+/// Interprets the constraints of a schema without `type` as a typed schema.
+fn any_to_type(any: &AnySchema) -> Option<Type> {
+    if !any.properties.is_empty()
+        || !any.required.is_empty()
+        || any.additional_properties.is_some()
+        || any.min_properties.is_some()
+        || any.max_properties.is_some()
+    {
+        Some(Type::Object(ObjectType {
+            properties: any.properties.clone(),
+            required: any.required.clone(),
+            additional_properties: any.additional_properties.clone(),
+            min_properties: any.min_properties,
+            max_properties: any.max_properties,
+        }))
+    } else if let Some(items) = &any.items {
+        Some(Type::Array(ArrayType {
+            items: items.clone(),
+            min_items: any.min_items,
+            max_items: any.max_items,
+            unique_items: any.unique_items.unwrap_or(false),
+        }))
+    } else if any.pattern.is_some() {
+        Some(Type::String(StringType {
+            pattern: any.pattern.clone(),
+            ..Default::default()
+        }))
+    } else if any.minimum.is_some() || any.maximum.is_some() || any.multiple_of.is_some() {
+        Some(Type::Number(NumberType {
+            minimum: any.minimum,
+            maximum: any.maximum,
+            exclusive_minimum: any.exclusive_minimum.unwrap_or(false),
+            exclusive_maximum: any.exclusive_maximum.unwrap_or(false),
+            multiple_of: any.multiple_of,
+            ..Default::default()
+        }))
+    } else {
+        None
+    }
+}
+
+fn type_name(schema_type: &Type) -> &'static str {
+    match schema_type {
+        Type::String(_) => "string",
+        Type::Number(_) => "number",
+        Type::Integer(_) => "integer",
+        Type::Object(_) => "object",
+        Type::Array(_) => "array",
+        Type::Boolean {} => "boolean",
+    }
+}
+
+fn merge_types(a: &Type, b: &Type, merging: &Merging) -> Result<Type> {
+    Ok(match (a, b) {
+        (Type::String(a), Type::String(b)) => Type::String(merge_strings(a, b)?),
+        (Type::Integer(a), Type::Integer(b)) => Type::Integer(merge_integers(a, b)?),
+        (Type::Number(a), Type::Number(b)) => Type::Number(merge_numbers(a, b)?),
+        (Type::Integer(integer), Type::Number(number))
+        | (Type::Number(number), Type::Integer(integer)) => {
+            Type::Integer(merge_integers(integer, &number_to_integer(number)?)?)
+        }
+        (Type::Object(a), Type::Object(b)) => Type::Object(merge_objects(a, b, merging)?),
+        (Type::Array(a), Type::Array(b)) => Type::Array(merge_arrays(a, b, merging)?),
+        (Type::Boolean {}, Type::Boolean {}) => Type::Boolean {},
+        (a, b) => bail!(
+            "incompatible types `{}` and `{}`",
+            type_name(a),
+            type_name(b)
+        ),
+    })
+}
+
+/// Merges two optional constraints, failing if both are present and differ.
+fn merge_equal<T: Clone + PartialEq + std::fmt::Debug>(
+    name: &str,
+    a: &Option<T>,
+    b: &Option<T>,
+) -> Result<Option<T>> {
+    match (a, b) {
+        (Some(a), Some(b)) if a != b => bail!("cannot combine {} {:?} and {:?}", name, a, b),
+        (a, b) => Ok(a.clone().or_else(|| b.clone())),
+    }
+}
+
+fn merge_enums<T: Clone + PartialEq>(a: &[T], b: &[T]) -> Result<Vec<T>> {
+    match (a.is_empty(), b.is_empty()) {
+        (true, _) => Ok(b.to_vec()),
+        (_, true) => Ok(a.to_vec()),
+        _ => {
+            let common: Vec<_> = a
+                .iter()
+                .filter(|value| b.contains(value))
+                .cloned()
+                .collect();
+            if common.is_empty() {
+                bail!("enums have no common value");
+            }
+            Ok(common)
+        }
+    }
+}
+
+fn merge_min<T: Ord + Copy>(a: Option<T>, b: Option<T>) -> Option<T> {
+    a.into_iter().chain(b).max()
+}
+
+fn merge_max<T: Ord + Copy>(a: Option<T>, b: Option<T>) -> Option<T> {
+    a.into_iter().chain(b).min()
+}
+
+fn check_range<T: PartialOrd + std::fmt::Display>(
+    name: &str,
+    min: Option<T>,
+    max: Option<T>,
+) -> Result<()> {
+    match (min, max) {
+        (Some(min), Some(max)) if min > max => {
+            bail!(
+                "minimum {0} {1} is greater than maximum {0} {2}",
+                name,
+                min,
+                max
+            )
+        }
+        _ => Ok(()),
+    }
+}
+
+fn merge_strings(a: &StringType, b: &StringType) -> Result<StringType> {
+    let format = match (&a.format, &b.format) {
+        (VariantOrUnknownOrEmpty::Empty, format) | (format, VariantOrUnknownOrEmpty::Empty) => {
+            format.clone()
+        }
+        (a, b) if a == b => a.clone(),
+        (a, b) => bail!("cannot combine formats {:?} and {:?}", a, b),
+    };
+    let merged = StringType {
+        format,
+        pattern: merge_equal("patterns", &a.pattern, &b.pattern)?,
+        enumeration: merge_enums(&a.enumeration, &b.enumeration)?,
+        min_length: merge_min(a.min_length, b.min_length),
+        max_length: merge_max(a.max_length, b.max_length),
+    };
+    check_range("length", merged.min_length, merged.max_length)?;
+    Ok(merged)
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Picks the stricter of two bounds, where `stricter` tells whether the first
+/// bound is stricter than the second one.
+fn merge_bound<T: PartialEq + Copy>(
+    a: (Option<T>, bool),
+    b: (Option<T>, bool),
+    stricter: impl Fn(T, T) -> bool,
+) -> (Option<T>, bool) {
+    match (a, b) {
+        ((Some(x), x_exclusive), (Some(y), y_exclusive)) if x == y => {
+            (Some(x), x_exclusive || y_exclusive)
+        }
+        ((Some(x), _), (Some(y), _)) if stricter(y, x) => b,
+        ((Some(_), _), _) => a,
+        _ => b,
+    }
+}
+
+fn merge_integers(a: &IntegerType, b: &IntegerType) -> Result<IntegerType> {
+    let int32 = VariantOrUnknownOrEmpty::Item(IntegerFormat::Int32);
+    let (minimum, exclusive_minimum) = merge_bound(
+        (a.minimum, a.exclusive_minimum),
+        (b.minimum, b.exclusive_minimum),
+        |x, y| x > y,
+    );
+    let (maximum, exclusive_maximum) = merge_bound(
+        (a.maximum, a.exclusive_maximum),
+        (b.maximum, b.exclusive_maximum),
+        |x, y| x < y,
+    );
+    let merged = IntegerType {
+        format: if a.format == int32 || b.format == int32 {
+            int32
+        } else {
+            a.format.clone()
+        },
+        multiple_of: match (a.multiple_of, b.multiple_of) {
+            (Some(x), Some(y)) if x != 0 && y != 0 => match (x / gcd(x, y)).checked_mul(y) {
+                Some(lcm) => Some(lcm),
+                None => bail!("cannot combine multipleOf {} and {}", x, y),
+            },
+            (x, y) => x.or(y),
+        },
+        exclusive_minimum,
+        exclusive_maximum,
+        minimum,
+        maximum,
+        enumeration: merge_enums(&a.enumeration, &b.enumeration)?,
+    };
+    check_range("value", merged.minimum, merged.maximum)?;
+    Ok(merged)
+}
+
+fn merge_numbers(a: &NumberType, b: &NumberType) -> Result<NumberType> {
+    let (minimum, exclusive_minimum) = merge_bound(
+        (a.minimum, a.exclusive_minimum),
+        (b.minimum, b.exclusive_minimum),
+        |x, y| x > y,
+    );
+    let (maximum, exclusive_maximum) = merge_bound(
+        (a.maximum, a.exclusive_maximum),
+        (b.maximum, b.exclusive_maximum),
+        |x, y| x < y,
+    );
+    let merged = NumberType {
+        format: match (&a.format, &b.format) {
+            (VariantOrUnknownOrEmpty::Empty, format) | (format, _) => format.clone(),
+        },
+        multiple_of: match (a.multiple_of, b.multiple_of) {
+            (Some(x), Some(y)) if is_multiple(x, y) => Some(x),
+            (Some(x), Some(y)) if is_multiple(y, x) => Some(y),
+            (Some(x), Some(y)) => bail!("cannot combine multipleOf {} and {}", x, y),
+            (x, y) => x.or(y),
+        },
+        exclusive_minimum,
+        exclusive_maximum,
+        minimum,
+        maximum,
+        enumeration: merge_enums(&a.enumeration, &b.enumeration)?,
+    };
+    check_range("value", merged.minimum, merged.maximum)?;
+    Ok(merged)
+}
+
+/// Whether `a` is a multiple of `b`, allowing for the rounding errors of floats,
+/// e.g. 0.3 / 0.1 is 2.9999999999999996.
+fn is_multiple(a: f64, b: f64) -> bool {
+    let quotient = a / b;
+    (quotient - quotient.round()).abs() <= 1e-9 * quotient.abs().max(1.)
+}
+
+fn number_to_integer(number: &NumberType) -> Result<IntegerType> {
+    let multiple_of = match number.multiple_of {
+        Some(step) if is_multiple(step, 1.) => Some(step.round() as i64),
+        // An integer is always a multiple of 1/n
+        Some(step) if is_multiple(1., step) => None,
+        Some(step) => bail!("cannot combine integer with multipleOf {}", step),
+        None => None,
+    };
+    Ok(IntegerType {
+        format: VariantOrUnknownOrEmpty::Empty,
+        multiple_of,
+        exclusive_minimum: number.exclusive_minimum
+            && number.minimum.is_some_and(|n| n.fract() == 0.),
+        exclusive_maximum: number.exclusive_maximum
+            && number.maximum.is_some_and(|n| n.fract() == 0.),
+        minimum: number.minimum.map(|n| n.ceil() as i64),
+        maximum: number.maximum.map(|n| n.floor() as i64),
+        enumeration: number
+            .enumeration
+            .iter()
+            .filter(|n| n.fract() == 0.)
+            .map(|n| *n as i64)
+            .collect(),
+    })
+}
+
+/// Merges two possibly referenced schemas, keeping the reference if both are
+/// the same.
+fn merge_referenced_schemas<T: Borrow<Schema>>(
+    a: &ReferenceOr<T>,
+    b: &ReferenceOr<T>,
+    merging: &Merging,
+) -> Result<ReferenceOr<Schema>> {
+    if let (ReferenceOr::Reference { reference: a }, ReferenceOr::Reference { reference: b }) =
+        (a, b)
+    {
+        if a == b {
+            return Ok(ReferenceOr::ref_(a));
+        }
+    }
+    merging
+        .resolved(a, |a| merging.resolved(b, |b| merge_schemas(a, b, merging)))
+        .map(ReferenceOr::Item)
+}
+
+fn boxed(schema: ReferenceOr<Schema>) -> ReferenceOr<Box<Schema>> {
+    match schema {
+        ReferenceOr::Item(schema) => ReferenceOr::boxed_item(schema),
+        ReferenceOr::Reference { reference } => ReferenceOr::Reference { reference },
+    }
+}
+
+fn merge_additional_properties(
+    a: &Option<AdditionalProperties>,
+    b: &Option<AdditionalProperties>,
+    merging: &Merging,
+) -> Result<Option<AdditionalProperties>> {
+    Ok(match (a, b) {
+        (None, other) | (other, None) => other.clone(),
+        (Some(AdditionalProperties::Any(false)), _)
+        | (_, Some(AdditionalProperties::Any(false))) => Some(AdditionalProperties::Any(false)),
+        (Some(AdditionalProperties::Any(true)), other)
+        | (other, Some(AdditionalProperties::Any(true))) => other.clone(),
+        (Some(AdditionalProperties::Schema(a)), Some(AdditionalProperties::Schema(b))) => {
+            let merged =
+                merge_referenced_schemas(a, b, merging).context("in additionalProperties")?;
+            Some(AdditionalProperties::Schema(Box::new(merged)))
+        }
+    })
+}
+
+fn merge_objects(a: &ObjectType, b: &ObjectType, merging: &Merging) -> Result<ObjectType> {
+    let mut properties = a.properties.clone();
+    for (name, schema) in &b.properties {
+        let merged = match properties.get(name) {
+            Some(existing) => merge_referenced_schemas(existing, schema, merging)
+                .map(boxed)
+                .context(format!("in property `{name}`"))?,
+            None => schema.clone(),
+        };
+        properties.insert(name.clone(), merged);
+    }
+
+    let mut required = a.required.clone();
+    required.extend(
+        b.required
+            .iter()
+            .filter(|name| !a.required.contains(name))
+            .cloned(),
+    );
+
+    let merged = ObjectType {
+        properties,
+        required,
+        additional_properties: merge_additional_properties(
+            &a.additional_properties,
+            &b.additional_properties,
+            merging,
+        )?,
+        min_properties: merge_min(a.min_properties, b.min_properties),
+        max_properties: merge_max(a.max_properties, b.max_properties),
+    };
+    check_range("properties", merged.min_properties, merged.max_properties)?;
+    Ok(merged)
+}
+
+fn merge_arrays(a: &ArrayType, b: &ArrayType, merging: &Merging) -> Result<ArrayType> {
+    let items = merge_referenced_schemas(&a.items, &b.items, merging)
+        .map(boxed)
+        .context("in array items")?;
+    let merged = ArrayType {
+        items,
+        min_items: merge_min(a.min_items, b.min_items),
+        max_items: merge_max(a.max_items, b.max_items),
+        unique_items: a.unique_items || b.unique_items,
+    };
+    check_range("items", merged.min_items, merged.max_items)?;
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use indexmap::indexmap;
+    use openapi_utils::ReferenceOrExt;
+
+    fn schema(schema_type: Type) -> ReferenceOr<Schema> {
+        ReferenceOr::Item(Schema {
+            schema_data: Default::default(),
+            schema_kind: SchemaKind::Type(schema_type),
+        })
+    }
+
+    fn object(properties: &[(&str, Type)], required: &[&str]) -> ReferenceOr<Schema> {
+        schema(Type::Object(ObjectType {
+            properties: properties
+                .iter()
+                .map(|(name, schema_type)| {
+                    (
+                        name.to_string(),
+                        ReferenceOr::boxed_item(schema(schema_type.clone()).to_item()),
+                    )
+                })
+                .collect(),
+            required: required.iter().map(|name| name.to_string()).collect(),
+            ..Default::default()
+        }))
+    }
+
+    #[test]
+    fn test_merge_objects() {
+        let merged = merge_all_of(
+            &[
+                object(
+                    &[(
+                        "id",
+                        Type::Integer(IntegerType {
+                            minimum: Some(0),
+                            ..Default::default()
+                        }),
+                    )],
+                    &["id"],
+                ),
+                object(
+                    &[
+                        (
+                            "id",
+                            Type::Number(NumberType {
+                                maximum: Some(10.5),
+                                ..Default::default()
+                            }),
+                        ),
+                        ("name", Type::String(Default::default())),
+                    ],
+                    &["name"],
+                ),
+            ],
+            &Resolver::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            merged.schema_kind,
+            SchemaKind::Type(Type::Object(ObjectType {
+                properties: indexmap! {
+                    "id".to_owned() => ReferenceOr::boxed_item(schema(Type::Integer(IntegerType {
+                        minimum: Some(0),
+                        maximum: Some(10),
+                        ..Default::default()
+                    })).to_item()),
+                    "name".to_owned() => ReferenceOr::boxed_item(
+                        schema(Type::String(Default::default())).to_item()
+                    ),
+                },
+                required: vec!["id".to_owned(), "name".to_owned()],
+                ..Default::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn test_merge_conflict() {
+        let error = merge_all_of(
+            &[
+                object(&[("zip", Type::String(Default::default()))], &[]),
+                object(&[("zip", Type::Boolean {})], &[]),
+            ],
+            &Resolver::default(),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            format!("{error:#}"),
+            "in allOf schema #1: in property `zip`: incompatible types `string` and `boolean`"
+        );
+    }
+
+    #[test]
+    fn test_merge_cyclic_reference() {
+        let node: ReferenceOr<Schema> = serde_json::from_value(serde_json::json!({
+            "allOf": [{ "$ref": "#/components/schemas/Node" }]
+        }))
+        .unwrap();
+        let resolver = Resolver::new(indexmap! { "Node".to_owned() => node }, Default::default());
+        let error =
+            merge_all_of(&[ReferenceOr::ref_("#/components/schemas/Node")], &resolver).unwrap_err();
+
+        assert_eq!(
+            format!("{error:#}"),
+            "in allOf schema #0: in allOf schema #0: cyclic reference `#/components/schemas/Node`"
+        );
+    }
+
+    #[test]
+    fn test_merge_multiple_of() {
+        let number = |multiple_of| {
+            schema(Type::Number(NumberType {
+                multiple_of: Some(multiple_of),
+                ..Default::default()
+            }))
+        };
+        let merged = merge_all_of(&[number(0.1), number(0.3)], &Resolver::default()).unwrap();
+        assert_eq!(merged.schema_kind, number(0.3).to_item().schema_kind);
+
+        let integer = |multiple_of| {
+            schema(Type::Integer(IntegerType {
+                multiple_of: Some(multiple_of),
+                ..Default::default()
+            }))
+        };
+        assert!(merge_all_of(&[integer(i64::MAX), integer(2)], &Resolver::default()).is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use proptest::{
+    arbitrary::any,
+    sample::{select, Index},
+    strategy::{BoxedStrategy, Strategy},
+};
+use serde_json::Value;
+
+/// Number of times a string is repeated to make it longer
+const STRING_REPETITIONS: usize = 64;
+
+/// Returns the JSON pointers of a value and of all the values nested in it.
+pub fn pointers(value: &Value, pointer: String, all: &mut Vec<String>) {
+    match value {
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                pointers(item, format!("{pointer}/{i}"), all);
+            }
+        }
+        Value::Object(object) => {
+            for (name, value) in object {
+                let token = name.replace('~', "~0").replace('/', "~1");
+                pointers(value, format!("{pointer}/{token}"), all);
+            }
+        }
+        _ => {}
+    }
+    all.push(pointer);
+}
+
+/// Changes a value slightly, e.g. a string to an empty or a longer one, a number
+/// to its neighbour, or removes a property or an item.
+fn mutate_value(value: &mut Value, choice: Index, filler: String) {
+    let mut mutations: Vec<Value> = match value {
+        Value::Null => vec![Value::String(filler.clone()), 0.into()],
+        Value::Bool(b) => vec![(!*b).into(), b.to_string().into()],
+        Value::Number(n) => match n.as_i64() {
+            Some(n) => vec![
+                n.saturating_add(1).into(),
+                n.saturating_sub(1).into(),
+                n.saturating_neg().into(),
+                0.into(),
+                (n as f64 + 0.5).into(),
+                n.to_string().into(),
+            ],
+            None => {
+                let n = n.as_f64().unwrap_or_default();
+                vec![
+                    (-n).into(),
+                    0.into(),
+                    (n * 1e10).into(),
+                    n.to_string().into(),
+                ]
+            }
+        },
+        Value::String(s) => vec![
+            String::new().into(),
+            format!("{s}{filler}").into(),
+            s.repeat(STRING_REPETITIONS).into(),
+            s.chars()
+                .take(s.chars().count() / 2)
+                .collect::<String>()
+                .into(),
+            vec![Value::String(s.clone())].into(),
+        ],
+        Value::Array(items) => {
+            let mut mutations = vec![Value::Array(vec![])];
+            if !items.is_empty() {
+                let mut removed = items.clone();
+                removed.remove(choice.index(items.len()));
+                let mut duplicated = items.clone();
+                duplicated.push(items[choice.index(items.len())].clone());
+                mutations.extend([removed.into(), duplicated.into()]);
+            }
+            mutations
+        }
+        Value::Object(object) => {
+            let mut mutations = vec![Value::Object(Default::default())];
+            if !object.is_empty() {
+                let mut removed = object.clone();
+                let name = object.keys().nth(choice.index(object.len())).cloned();
+                removed.remove(&name.unwrap_or_default());
+                mutations.push(removed.into());
+            }
+            let mut added = object.clone();
+            added.insert(filler.clone(), Value::String(filler));
+            mutations.push(added.into());
+            mutations
+        }
+    };
+    mutations.push(Value::Null);
+    *value = mutations.swap_remove(choice.index(mutations.len()));
+}
+
+/// Generates variations of a value, e.g. of an example from the specification,
+/// by mutating the value itself or one of the values nested in it.
+pub fn mutate_json(value: Value) -> BoxedStrategy<Value> {
+    let mut all = vec![];
+    pointers(&value, String::new(), &mut all);
+    (select(all), any::<Index>(), "[a-zA-Z0-9_]{1,8}")
+        .prop_map(move |(pointer, choice, filler)| {
+            let mut value = value.clone();
+            if let Some(target) = value.pointer_mut(&pointer) {
+                mutate_value(target, choice, filler);
+            }
+            value
+        })
+        .boxed()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::{prop_assert_ne, proptest};
+    use serde_json::json;
+
+    #[test]
+    fn test_pointers() {
+        let mut all = vec![];
+        pointers(&json!({ "a/b": [1], "c": 2 }), String::new(), &mut all);
+        assert_eq!(all, ["/a~1b/0", "/a~1b", "/c", ""]);
+    }
+
+    proptest! {
+        #[test]
+        fn test_mutate_json(value in mutate_json(json!({ "name": "Rex", "tags": ["a", 1] }))) {
+            prop_assert_ne!(value, json!({ "name": "Rex", "tags": ["a", 1] }));
+        }
+    }
+}
@@ -0,0 +1,544 @@
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use openapiv3::{ReferenceOr, Schema};
+use serde_json::{json, Map, Value};
+
+const SCHEMAS: &str = "/components/schemas/";
+
+const METHODS: &[&str] = &[
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Keywords of JSON Schema 2020-12 by the name of the schema component they
+/// belong to
+pub type KeywordMap = IndexMap<String, Keywords>;
+
+/// Keywords of a schema that `openapiv3` does not model, as OpenAPI 3.0 has no
+/// equivalent for them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Keywords {
+    /// The only allowed value, from `const`
+    pub constant: Option<Value>,
+    /// Schemas of the leading items of an array, from `prefixItems`
+    pub prefix_items: Vec<ReferenceOr<Schema>>,
+    /// Values of `examples`
+    pub examples: Vec<Value>,
+    /// Whether no value is valid, as for the `false` schema
+    pub is_false: bool,
+}
+
+/// Converts an OpenAPI 3.1 document to the 3.0 form read by `openapiv3`, e.g.
+/// `type: [string, "null"]` to `type: string` and `nullable: true`. Schemas
+/// using `const`, `prefixItems` or `examples` and `false` schemas are moved to
+/// the components, so that their keywords can be looked up by reference, and
+/// `$defs` are moved there as well.
+pub fn convert(document: &mut Value) -> Result<KeywordMap> {
+    let mut converter = Converter::default();
+    converter.document(document);
+
+    let object = match document.as_object_mut() {
+        Some(object) => object,
+        None => return Ok(KeywordMap::new()),
+    };
+    object.entry("paths").or_insert_with(|| json!({}));
+    let components = object
+        .entry("components")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .context("Invalid components: expected an object")?;
+    let schemas = components
+        .entry("schemas")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .context("Invalid schemas: expected an object")?;
+    let mut moved = IndexMap::new();
+    for (pointer, schema) in converter.hoisted {
+        let name = component_name(&pointer);
+        moved.insert(
+            format!("#{pointer}"),
+            format!("#{SCHEMAS}{}", escape(&name)),
+        );
+        schemas.insert(name, schema);
+    }
+    rewrite_references(document, &moved);
+
+    let mut keywords = KeywordMap::new();
+    let schemas = document["components"]["schemas"].as_object().into_iter();
+    for (name, schema) in schemas.flatten() {
+        if let Some(schema_keywords) =
+            keywords_of(schema).context(format!("Invalid schema `{name}`"))?
+        {
+            keywords.insert(name.clone(), schema_keywords);
+        }
+    }
+    Ok(keywords)
+}
+
+fn keywords_of(schema: &Value) -> Result<Option<Keywords>> {
+    let constant = schema.get("const").cloned();
+    let prefix_items = match schema.get("prefixItems") {
+        Some(items) => serde_json::from_value(items.clone()).context("Invalid prefixItems")?,
+        None => vec![],
+    };
+    let examples = match schema.get("examples") {
+        Some(Value::Array(examples)) => examples.clone(),
+        _ => vec![],
+    };
+    let is_false = schema.as_object().is_some_and(is_false);
+    if constant.is_none() && prefix_items.is_empty() && examples.is_empty() && !is_false {
+        return Ok(None);
+    }
+    Ok(Some(Keywords {
+        constant,
+        prefix_items,
+        examples,
+        is_false,
+    }))
+}
+
+/// Whether the schema is `{ not: {} }`, which the `false` schema is converted to
+fn is_false(schema: &Map<String, Value>) -> bool {
+    schema
+        .get("not")
+        .and_then(Value::as_object)
+        .is_some_and(Map::is_empty)
+}
+
+fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Name of the component a schema found at `pointer` is moved to, e.g.
+/// `Pet/$defs/Tag` for `/components/schemas/Pet/$defs/Tag`, so that the original
+/// reference still points to it.
+fn component_name(pointer: &str) -> String {
+    let relative = pointer
+        .strip_prefix(SCHEMAS)
+        .unwrap_or_else(|| pointer.trim_start_matches('/'));
+    relative
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn rewrite_references(value: &mut Value, moved: &IndexMap<String, String>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                match value {
+                    Value::String(reference) if key == "$ref" => {
+                        if let Some(new) = moved.get(reference.as_str()) {
+                            *reference = new.clone();
+                        }
+                    }
+                    value => rewrite_references(value, moved),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                rewrite_references(item, moved);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Walks the parts of a document that hold schemas
+#[derive(Default)]
+struct Converter {
+    /// Converted schemas moved to the components by their original pointer
+    hoisted: IndexMap<String, Value>,
+}
+
+impl Converter {
+    fn document(&mut self, document: &mut Value) {
+        if let Some(paths) = document.get_mut("paths").and_then(Value::as_object_mut) {
+            for (path, item) in paths {
+                self.path_item(item, &format!("/paths/{}", escape(path)));
+            }
+        }
+        let components = match document.get_mut("components") {
+            Some(components) => components,
+            None => return,
+        };
+        let pointer = "/components";
+        for_each(components, "schemas", pointer, |schema, pointer| {
+            self.schema(schema, pointer)
+        });
+        for_each(components, "parameters", pointer, |parameter, pointer| {
+            self.parameter(parameter, pointer)
+        });
+        for_each(components, "headers", pointer, |header, pointer| {
+            self.parameter(header, pointer)
+        });
+        for_each(components, "requestBodies", pointer, |body, pointer| {
+            self.content(body, pointer)
+        });
+        for_each(components, "responses", pointer, |response, pointer| {
+            self.response(response, pointer)
+        });
+        for_each(components, "callbacks", pointer, |callback, pointer| {
+            self.callback(callback, pointer)
+        });
+    }
+
+    fn path_item(&mut self, item: &mut Value, pointer: &str) {
+        for_each(item, "parameters", pointer, |parameter, pointer| {
+            self.parameter(parameter, pointer)
+        });
+        for method in METHODS {
+            if let Some(operation) = item.get_mut(*method) {
+                self.operation(operation, &format!("{pointer}/{method}"));
+            }
+        }
+    }
+
+    fn operation(&mut self, operation: &mut Value, pointer: &str) {
+        for_each(operation, "parameters", pointer, |parameter, pointer| {
+            self.parameter(parameter, pointer)
+        });
+        if let Some(body) = operation.get_mut("requestBody") {
+            self.content(body, &format!("{pointer}/requestBody"));
+        }
+        for_each(operation, "responses", pointer, |response, pointer| {
+            self.response(response, pointer)
+        });
+        for_each(operation, "callbacks", pointer, |callback, pointer| {
+            self.callback(callback, pointer)
+        });
+    }
+
+    fn callback(&mut self, callback: &mut Value, pointer: &str) {
+        if let Some(items) = callback.as_object_mut() {
+            for (expression, item) in items {
+                self.path_item(item, &format!("{pointer}/{}", escape(expression)));
+            }
+        }
+    }
+
+    fn response(&mut self, response: &mut Value, pointer: &str) {
+        for_each(response, "headers", pointer, |header, pointer| {
+            self.parameter(header, pointer)
+        });
+        self.content(response, pointer);
+    }
+
+    /// Converts the schema of a parameter or header
+    fn parameter(&mut self, parameter: &mut Value, pointer: &str) {
+        if let Some(schema) = parameter.get_mut("schema") {
+            self.schema(schema, &format!("{pointer}/schema"));
+        }
+        self.content(parameter, pointer);
+    }
+
+    /// Converts the schemas of the `content` of an object
+    fn content(&mut self, object: &mut Value, pointer: &str) {
+        for_each(object, "content", pointer, |media_type, pointer| {
+            if let Some(schema) = media_type.get_mut("schema") {
+                self.schema(schema, &format!("{pointer}/schema"));
+            }
+        });
+    }
+
+    fn schema(&mut self, schema: &mut Value, pointer: &str) {
+        // Boolean schemas
+        match schema {
+            Value::Bool(true) => *schema = json!({}),
+            Value::Bool(false) => *schema = json!({ "not": {} }),
+            _ => {}
+        }
+        let object = match schema.as_object_mut() {
+            Some(object) if !object.contains_key("$ref") => object,
+            _ => return,
+        };
+
+        if object.get("items") == Some(&Value::Bool(false)) {
+            let prefix = object
+                .get("prefixItems")
+                .and_then(Value::as_array)
+                .map_or(0, Vec::len);
+            object.insert("items".to_owned(), json!({}));
+            object.insert("maxItems".to_owned(), prefix.into());
+        }
+        for key in &["items", "not", "contains", "if", "then", "else"] {
+            if let Some(subschema) = object.get_mut(*key) {
+                self.schema(subschema, &format!("{pointer}/{key}"));
+            }
+        }
+        if let Some(subschema) = object.get_mut("additionalProperties") {
+            if subschema.is_object() {
+                self.schema(subschema, &format!("{pointer}/additionalProperties"));
+            }
+        }
+        // Properties with the `false` schema must not be sent, so leave them out
+        if let Some(Value::Object(properties)) = object.get_mut("properties") {
+            let forbidden: Vec<_> = properties
+                .iter()
+                .filter(|(_, property)| **property == Value::Bool(false))
+                .map(|(name, _)| name.clone())
+                .collect();
+            for name in forbidden {
+                properties.remove(&name);
+            }
+        }
+        for key in &["properties", "patternProperties", "dependentSchemas"] {
+            if let Some(Value::Object(subschemas)) = object.get_mut(*key) {
+                for (name, subschema) in subschemas {
+                    self.schema(subschema, &format!("{pointer}/{key}/{}", escape(name)));
+                }
+            }
+        }
+        for key in &["allOf", "anyOf", "oneOf", "prefixItems"] {
+            if let Some(Value::Array(subschemas)) = object.get_mut(*key) {
+                for (i, subschema) in subschemas.iter_mut().enumerate() {
+                    self.schema(subschema, &format!("{pointer}/{key}/{i}"));
+                }
+            }
+        }
+        if let Some(Value::Object(definitions)) = object.remove("$defs") {
+            for (name, mut definition) in definitions {
+                let pointer = format!("{pointer}/$defs/{}", escape(&name));
+                self.schema(&mut definition, &pointer);
+                self.hoisted.insert(pointer, definition);
+            }
+        }
+
+        convert_keywords(object);
+        let is_component = pointer
+            .strip_prefix(SCHEMAS)
+            .is_some_and(|name| !name.contains('/'));
+        let has_keywords = object.contains_key("const")
+            || object.contains_key("prefixItems")
+            || object.get("examples").is_some_and(Value::is_array)
+            || is_false(object);
+        if has_keywords && !is_component {
+            let moved = std::mem::replace(schema, json!({ "$ref": format!("#{pointer}") }));
+            self.hoisted.insert(pointer.to_owned(), moved);
+        }
+    }
+}
+
+/// Calls `f` with each value of the map or array under `key` of `object`.
+fn for_each(object: &mut Value, key: &str, pointer: &str, mut f: impl FnMut(&mut Value, &str)) {
+    match object.get_mut(key) {
+        Some(Value::Object(values)) => {
+            for (name, value) in values {
+                f(value, &format!("{pointer}/{key}/{}", escape(name)));
+            }
+        }
+        Some(Value::Array(values)) => {
+            for (i, value) in values.iter_mut().enumerate() {
+                f(value, &format!("{pointer}/{key}/{i}"));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn json_type(value: &Value) -> Option<&'static str> {
+    match value {
+        Value::Null => None,
+        Value::Bool(_) => Some("boolean"),
+        Value::Number(n) if n.is_f64() => Some("number"),
+        Value::Number(_) => Some("integer"),
+        Value::String(_) => Some("string"),
+        Value::Array(_) => Some("array"),
+        Value::Object(_) => Some("object"),
+    }
+}
+
+/// Replaces the keywords of a schema that differ from OpenAPI 3.0 with their
+/// closest 3.0 equivalent. `const`, `prefixItems` and `examples` are kept for
+/// the generators.
+fn convert_keywords(object: &mut Map<String, Value>) {
+    // Numeric exclusive bounds, of which the stricter one of the inclusive and
+    // the exclusive bound is kept
+    for (bound, exclusive, sign) in &[
+        ("minimum", "exclusiveMinimum", 1.),
+        ("maximum", "exclusiveMaximum", -1.),
+    ] {
+        let exclusive_value = match object.get(*exclusive).filter(|value| value.is_number()) {
+            Some(value) => value.clone(),
+            None => continue,
+        };
+        let is_stricter = match (
+            object.get(*bound).and_then(Value::as_f64),
+            exclusive_value.as_f64(),
+        ) {
+            (Some(inclusive), Some(exclusive)) => sign * exclusive >= sign * inclusive,
+            _ => true,
+        };
+        if is_stricter {
+            object.insert(bound.to_string(), exclusive_value);
+        }
+        object.insert(exclusive.to_string(), is_stricter.into());
+    }
+
+    match object.get("type").cloned() {
+        Some(Value::Array(types)) => {
+            let nullable = types.iter().any(|t| t == "null");
+            let types: Vec<_> = types.into_iter().filter(|t| t != "null").collect();
+            object.remove("type");
+            match types.as_slice() {
+                [] => {
+                    object.entry("const").or_insert(Value::Null);
+                }
+                [single] => {
+                    object.insert("type".to_owned(), single.clone());
+                }
+                _ => {
+                    let branches = types
+                        .iter()
+                        .map(|t| {
+                            let mut branch = object.clone();
+                            branch.insert("type".to_owned(), t.clone());
+                            Value::Object(branch)
+                        })
+                        .collect();
+                    object.insert("anyOf".to_owned(), Value::Array(branches));
+                }
+            }
+            if nullable {
+                object.insert("nullable".to_owned(), true.into());
+            }
+        }
+        Some(Value::String(t)) if t == "null" => {
+            object.remove("type");
+            object.insert("nullable".to_owned(), true.into());
+            object.entry("const").or_insert(Value::Null);
+        }
+        _ => {}
+    }
+
+    if let Some(Value::Array(values)) = object.get_mut("enum") {
+        if values.iter().any(Value::is_null) {
+            values.retain(|value| !value.is_null());
+            object.insert("nullable".to_owned(), true.into());
+        }
+    }
+
+    if let Some(constant) = object.get("const").cloned() {
+        match json_type(&constant) {
+            Some(t) => {
+                object.entry("type").or_insert_with(|| t.into());
+            }
+            None => {
+                object.insert("nullable".to_owned(), true.into());
+            }
+        }
+        if constant.is_string() || constant.is_number() {
+            object.insert("enum".to_owned(), json!([constant]));
+        }
+    }
+
+    if let Some(first) = object
+        .get("examples")
+        .and_then(Value::as_array)
+        .and_then(|examples| examples.first())
+        .cloned()
+    {
+        object.entry("example").or_insert(first);
+    }
+
+    if object.contains_key("prefixItems") {
+        object.entry("type").or_insert_with(|| "array".into());
+        object.entry("items").or_insert_with(|| json!({}));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use openapi_utils::ReferenceOrExt;
+    use openapiv3::{OpenAPI, SchemaKind, Type};
+
+    #[test]
+    fn test_convert() {
+        let mut document: Value = serde_yaml::from_str(
+            r##"
+openapi: 3.1.0
+info: { title: test, version: "1" }
+paths:
+  /pets:
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              type: object
+              properties:
+                kind: { const: dog }
+                name: { type: [string, "null"], examples: [Rex, Fido] }
+                position:
+                  type: array
+                  prefixItems: [{ type: number }, { $ref: "#/components/schemas/Pet/$defs/Unit" }]
+                  items: false
+                age: { type: integer, exclusiveMinimum: 0 }
+                weight: { type: number, minimum: 1, exclusiveMinimum: 0, maximum: 10, exclusiveMaximum: 10 }
+                secret: false
+      responses:
+        "200": { description: ok }
+components:
+  schemas:
+    Pet:
+      $defs:
+        Unit: { type: string, enum: [m, km] }
+    Never: false
+"##,
+        )
+        .unwrap();
+        let keywords = convert(&mut document).unwrap();
+        let openapi: OpenAPI = serde_json::from_value(document).unwrap();
+
+        let schemas = &openapi.components.unwrap().schemas;
+        assert!(schemas.contains_key("Pet/$defs/Unit"));
+        assert!(keywords["Never"].is_false);
+        let kind = "paths//pets/post/requestBody/content/application/json/schema/properties/kind";
+        assert_eq!(keywords[kind].constant, Some(json!("dog")));
+        assert!(matches!(
+            &schemas[kind].to_item_ref().schema_kind,
+            SchemaKind::Type(Type::String(string)) if string.enumeration == ["dog"]
+        ));
+        let name = kind.replace("kind", "name");
+        assert_eq!(keywords[&name].examples, [json!("Rex"), json!("Fido")]);
+        assert!(schemas[&name].to_item_ref().schema_data.nullable);
+        let position = &keywords[&kind.replace("kind", "position")];
+        assert_eq!(
+            position.prefix_items[1],
+            ReferenceOr::ref_("#/components/schemas/Pet~1$defs~1Unit")
+        );
+
+        let body = openapi.paths["/pets"].to_item_ref().post.as_ref().unwrap();
+        let schema = body.request_body.as_ref().unwrap().to_item_ref().content["application/json"]
+            .schema
+            .as_ref()
+            .unwrap()
+            .to_item_ref();
+        match &schema.schema_kind {
+            SchemaKind::Type(Type::Object(object)) => {
+                assert!(matches!(
+                    object.properties["age"].to_item_ref().schema_kind,
+                    SchemaKind::Type(Type::Integer(openapiv3::IntegerType {
+                        minimum: Some(0),
+                        exclusive_minimum: true,
+                        ..
+                    }))
+                ));
+                assert!(matches!(
+                    object.properties["weight"].to_item_ref().schema_kind,
+                    SchemaKind::Type(Type::Number(openapiv3::NumberType {
+                        minimum: Some(min),
+                        exclusive_minimum: false,
+                        maximum: Some(max),
+                        exclusive_maximum: true,
+                        ..
+                    })) if min == 1. && max == 10.
+                ));
+                assert!(!object.properties.contains_key("secret"));
+            }
+            kind => panic!("expected an object, got {:?}", kind),
+        }
+    }
+}
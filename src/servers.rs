@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+use openapiv3::Server;
+use url::Url;
+
+/// Decides where requests are sent: to the url given on the command line or to a
+/// server of the specification, with its variables substituted
+#[derive(Debug)]
+pub struct Servers {
+    /// Url from the command line, which replaces the servers of the specification
+    url: Option<Url>,
+    /// Index of the server picked from the servers of the specification
+    index: usize,
+    /// Values of server variables from the command line
+    variables: HashMap<String, String>,
+}
+
+impl Servers {
+    pub fn new(url: Option<Url>, index: usize, variables: HashMap<String, String>) -> Self {
+        Servers {
+            url,
+            index,
+            variables,
+        }
+    }
+
+    /// Returns the base url of an operation. The servers of the operation take
+    /// precedence over those of its path item, which take precedence over those
+    /// of the specification. Servers of operations and path items are picked by
+    /// the index too if they are numerous enough, otherwise their first one is.
+    /// The url from the command line replaces absolute servers, while relative
+    /// ones, e.g. `/api/v1`, are resolved against it.
+    pub fn url(
+        &self,
+        operation: &[Server],
+        path_item: &[Server],
+        specification: &[Server],
+    ) -> Result<Url> {
+        let server = match (operation, path_item) {
+            (servers @ [_, ..], _) | ([], servers @ [_, ..]) => {
+                servers.get(self.index).or(servers.first())
+            }
+            ([], []) => specification.get(self.index),
+        };
+        match (&self.url, server) {
+            (Some(url), Some(server)) if !server.url.contains("://") => {
+                let relative = self.substitute(server)?;
+                url.join(relative.trim_start_matches('/')).context(format!(
+                    "Invalid server url `{relative}` relative to `{url}`"
+                ))
+            }
+            (Some(url), _) => Ok(url.clone()),
+            (None, Some(server)) => {
+                let url = self.substitute(server)?;
+                Url::parse(&url).context(format!(
+                    "Invalid server url `{url}`: relative server urls need the url of the api passed with -u"
+                ))
+            }
+            (None, None) if specification.is_empty() => bail!(
+                "No url to send requests to, the specification has no servers: pass it with -u"
+            ),
+            (None, None) => bail!(
+                "Invalid server index {}, the specification has {} servers",
+                self.index,
+                specification.len()
+            ),
+        }
+    }
+
+    /// Replaces the `{variables}` in the url of a server with their values from
+    /// the command line or their defaults.
+    fn substitute(&self, server: &Server) -> Result<String> {
+        let mut url = String::with_capacity(server.url.len());
+        let mut rest = server.url.as_str();
+        while let Some(start) = rest.find('{') {
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => bail!("Invalid server url `{}`: unclosed `{{`", server.url),
+            };
+            let name = &rest[start + 1..end];
+            let variable = server
+                .variables
+                .as_ref()
+                .and_then(|variables| variables.get(name));
+            let value = match (self.variables.get(name), variable) {
+                (Some(value), Some(variable))
+                    if !variable.enumeration.is_empty()
+                        && !variable.enumeration.contains(value) =>
+                {
+                    bail!(
+                        "Invalid value `{}` of server variable `{}`, expected one of: {}",
+                        value,
+                        name,
+                        variable.enumeration.join(", ")
+                    )
+                }
+                (Some(value), _) => value,
+                (None, Some(variable)) => &variable.default,
+                (None, None) => bail!(
+                    "Server url `{}` uses variable `{}` without a value: pass it with --server-var {}=<value>",
+                    server.url,
+                    name,
+                    name
+                ),
+            };
+            url.push_str(&rest[..start]);
+            url.push_str(value);
+            rest = &rest[end + 1..];
+        }
+        url.push_str(rest);
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+        Ok(url)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn servers(yaml: &str) -> Vec<Server> {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_server_url() {
+        let specification = servers(
+            r#"
+- url: https://{region}.example.com:{port}/v1
+  variables:
+    region: { default: us, enum: [us, eu] }
+    port: { default: "443" }
+- url: http://localhost:8080/v1
+"#,
+        );
+        let operation = servers("[{ url: https://uploads.example.com }]");
+        let variables: HashMap<_, _> = vec![("region".to_owned(), "eu".to_owned())]
+            .into_iter()
+            .collect();
+
+        let url = Servers::new(None, 0, variables.clone()).url(&[], &[], &specification);
+        assert_eq!(url.unwrap().as_str(), "https://eu.example.com/v1/");
+        let url = Servers::new(None, 1, variables).url(&operation, &[], &specification);
+        assert_eq!(url.unwrap().as_str(), "https://uploads.example.com/");
+
+        let invalid = vec![("region".to_owned(), "ap".to_owned())]
+            .into_iter()
+            .collect();
+        let error = Servers::new(None, 0, invalid)
+            .url(&[], &[], &specification)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid value `ap` of server variable `region`, expected one of: us, eu"
+        );
+        assert!(Servers::new(None, 2, HashMap::new())
+            .url(&[], &[], &specification)
+            .is_err());
+
+        let base = Url::parse("http://localhost:8080/").unwrap();
+        let relative = servers("[{ url: /api/v1 }]");
+        let url = Servers::new(Some(base.clone()), 0, HashMap::new()).url(&[], &[], &relative);
+        assert_eq!(url.unwrap().as_str(), "http://localhost:8080/api/v1/");
+        let url = Servers::new(Some(base), 0, HashMap::new()).url(&operation, &[], &relative);
+        assert_eq!(url.unwrap().as_str(), "http://localhost:8080/");
+    }
+}
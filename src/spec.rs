@@ -0,0 +1,354 @@
+use std::{borrow::Borrow, fs, path::Path};
+
+use anyhow::{anyhow, bail, Context, Result};
+use indexmap::IndexMap;
+use openapi_utils::ParameterExt;
+use openapiv3::{
+    Components, MediaType, OpenAPI, Parameter, ParameterSchemaOrContent, ReferenceOr, Schema,
+};
+use serde_json::Value;
+
+use crate::{
+    bundle,
+    openapi31::{self, KeywordMap, Keywords},
+    swagger,
+};
+
+/// Maximum number of `$ref`s followed to get from a reference to an item
+const MAX_REFERENCE_CHAIN: usize = 32;
+
+/// Reads a specification in YAML or JSON and bundles the files it references.
+/// Swagger 2.0 and OpenAPI 3.1 documents are converted to OpenAPI 3.0 and the
+/// keywords of 3.1 that 3.0 has no equivalent for are returned by schema.
+pub fn load(path: &Path) -> Result<(OpenAPI, KeywordMap)> {
+    let content = fs::read_to_string(path).context(format!("Unable to read {path:?}"))?;
+    let mut document: Value = serde_yaml::from_str(&content).context("Failed to parse schema")?;
+    let bundled = bundle::bundle(path, &mut document)?;
+    let (mut openapi, keywords): (OpenAPI, _) =
+        match document.get("openapi").and_then(Value::as_str) {
+            Some(version) if version.starts_with("3.1") => {
+                let keywords = openapi31::convert(&mut document)?;
+                let openapi = serde_json::from_value(document).context("Failed to parse schema")?;
+                (openapi, keywords)
+            }
+            _ if document.get("swagger").and_then(Value::as_str) == Some("2.0") => {
+                let converted = swagger::convert(&document)?;
+                let openapi =
+                    serde_json::from_value(converted).context("Failed to parse schema")?;
+                (openapi, KeywordMap::new())
+            }
+            _ if bundled => (
+                serde_json::from_value(document).context("Failed to parse schema")?,
+                KeywordMap::new(),
+            ),
+            _ => (
+                serde_yaml::from_str(&content).context("Failed to parse schema")?,
+                KeywordMap::new(),
+            ),
+        };
+    inline_references(&mut openapi)?;
+    Ok((openapi, keywords))
+}
+
+/// Replaces references to parameters, request bodies, responses, headers and
+/// examples with the referenced components and copies the parameters of path
+/// items to their operations, unless an operation declares a parameter with the
+/// same name and location. References to schemas are kept, as schemas may be
+/// recursive, and are resolved lazily with a [`Resolver`] while generating values.
+pub fn inline_references(openapi: &mut OpenAPI) -> Result<()> {
+    let components = openapi.components.clone().unwrap_or_default();
+    for (path, ref_or_item) in &mut openapi.paths {
+        let item = match ref_or_item {
+            ReferenceOr::Item(item) => item,
+            ReferenceOr::Reference { reference } => {
+                bail!(
+                    "Reference `{}` to path item `{}` is not supported",
+                    reference,
+                    path
+                )
+            }
+        };
+        inline_path_item(item, &components).context(format!("Invalid path `{path}`"))?;
+    }
+    Ok(())
+}
+
+fn inline_path_item(item: &mut openapiv3::PathItem, components: &Components) -> Result<()> {
+    for parameter in &mut item.parameters {
+        inline_parameter(parameter, components)?;
+    }
+    let path_parameters = item.parameters.clone();
+    let operations = vec![
+        &mut item.get,
+        &mut item.put,
+        &mut item.post,
+        &mut item.delete,
+        &mut item.options,
+        &mut item.head,
+        &mut item.patch,
+        &mut item.trace,
+    ];
+    for operation in operations.into_iter().flatten() {
+        for parameter in &mut operation.parameters {
+            inline_parameter(parameter, components)?;
+        }
+        let overridden: Vec<_> = operation.parameters.iter().filter_map(key).collect();
+        operation.parameters.extend(
+            path_parameters
+                .iter()
+                .filter(|parameter| key(parameter).is_none_or(|key| !overridden.contains(&key)))
+                .cloned(),
+        );
+        if let Some(request_body) = &mut operation.request_body {
+            inline(request_body, &components.request_bodies, "requestBodies")?;
+            if let ReferenceOr::Item(request_body) = request_body {
+                for media_type in request_body.content.values_mut() {
+                    inline_examples(media_type, components)?;
+                }
+            }
+        }
+        for response in operation.responses.responses.values_mut() {
+            inline(response, &components.responses, "responses")?;
+            if let ReferenceOr::Item(response) = response {
+                for header in response.headers.values_mut() {
+                    inline(header, &components.headers, "headers")?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Identifies a parameter by its name and location
+fn key(parameter: &ReferenceOr<Parameter>) -> Option<(String, String)> {
+    match parameter {
+        ReferenceOr::Item(parameter) => {
+            Some((parameter.name().to_owned(), parameter.location_string()))
+        }
+        ReferenceOr::Reference { .. } => None,
+    }
+}
+
+fn inline_parameter(parameter: &mut ReferenceOr<Parameter>, components: &Components) -> Result<()> {
+    inline(parameter, &components.parameters, "parameters")?;
+    if let ReferenceOr::Item(parameter) = parameter {
+        let parameter_data = parameter.parameter_data_mut();
+        for example in parameter_data.examples.values_mut() {
+            inline(example, &components.examples, "examples")?;
+        }
+        if let ParameterSchemaOrContent::Content(content) = &mut parameter_data.format {
+            for media_type in content.values_mut() {
+                inline_examples(media_type, components)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn inline_examples(media_type: &mut MediaType, components: &Components) -> Result<()> {
+    for example in media_type.examples.values_mut() {
+        inline(example, &components.examples, "examples")?;
+    }
+    Ok(())
+}
+
+fn inline<T: Clone>(
+    ref_or_item: &mut ReferenceOr<T>,
+    components: &IndexMap<String, ReferenceOr<T>>,
+    kind: &str,
+) -> Result<()> {
+    if let ReferenceOr::Reference { reference } = ref_or_item {
+        *ref_or_item = ReferenceOr::Item(find_component(reference, components, kind)?.clone());
+    }
+    Ok(())
+}
+
+/// Looks up the component a reference such as `#/components/schemas/Pet` points
+/// to, following references between components.
+fn find_component<'a, T>(
+    reference: &str,
+    components: &'a IndexMap<String, ReferenceOr<T>>,
+    kind: &str,
+) -> Result<&'a T> {
+    find_named_component(reference, components, kind).map(|(_, item)| item)
+}
+
+/// Looks up a component like [`find_component`] and returns its name as well.
+fn find_named_component<'a, T>(
+    reference: &str,
+    components: &'a IndexMap<String, ReferenceOr<T>>,
+    kind: &str,
+) -> Result<(String, &'a T)> {
+    let mut current = reference;
+    for _ in 0..MAX_REFERENCE_CHAIN {
+        let name = component_name(current, kind)?;
+        match components.get(&name) {
+            Some(ReferenceOr::Item(item)) => return Ok((name, item)),
+            Some(ReferenceOr::Reference { reference }) => current = reference,
+            None => bail!(
+                "Unable to resolve reference `{}`: no such component",
+                current
+            ),
+        }
+    }
+    bail!(
+        "Unable to resolve reference `{}`: too many nested references",
+        reference
+    )
+}
+
+fn component_name(reference: &str, kind: &str) -> Result<String> {
+    let prefix = format!("#/components/{kind}/");
+    let name = reference.strip_prefix(&prefix).ok_or_else(|| {
+        anyhow!(
+            "Unable to resolve reference `{}`: expected a reference starting with `{}`",
+            reference,
+            prefix
+        )
+    })?;
+    // Unescape the JSON pointer token
+    Ok(name.replace("~1", "/").replace("~0", "~"))
+}
+
+/// Looks up the schemas that references point to.
+#[derive(Debug, Default)]
+pub struct Resolver {
+    schemas: IndexMap<String, ReferenceOr<Schema>>,
+    keywords: KeywordMap,
+}
+
+impl Resolver {
+    pub fn new(schemas: IndexMap<String, ReferenceOr<Schema>>, keywords: KeywordMap) -> Self {
+        Resolver { schemas, keywords }
+    }
+
+    /// Returns the OpenAPI 3.1 keywords of the schema that `reference` points to.
+    pub fn keywords(&self, reference: &str) -> Option<&Keywords> {
+        let (name, _) = find_named_component(reference, &self.schemas, "schemas").ok()?;
+        self.keywords.get(&name)
+    }
+
+    /// Returns the schema that `reference` points to.
+    pub fn schema(&self, reference: &str) -> Result<&Schema> {
+        find_component(reference, &self.schemas, "schemas")
+    }
+
+    /// Returns the schema itself or the one it references.
+    pub fn resolve<'a, T: Borrow<Schema>>(
+        &'a self,
+        ref_or_schema: &'a ReferenceOr<T>,
+    ) -> Result<&'a Schema> {
+        match ref_or_schema {
+            ReferenceOr::Item(schema) => Ok(schema.borrow()),
+            ReferenceOr::Reference { reference } => self.schema(reference),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use openapi_utils::ReferenceOrExt;
+    use openapiv3::{SchemaKind, Type};
+
+    #[test]
+    fn test_inline_references() {
+        let mut openapi: OpenAPI = serde_yaml::from_str(
+            r##"
+openapi: 3.0.0
+info: { title: test, version: "1" }
+paths:
+  /pets/{id}:
+    parameters:
+      - $ref: "#/components/parameters/Id"
+      - { name: limit, in: header, schema: { type: string } }
+      - { name: limit, in: query, schema: { type: string } }
+    get:
+      parameters:
+        - $ref: "#/components/parameters/Alias"
+      responses:
+        "200":
+          description: a pet
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Pet"
+components:
+  parameters:
+    Alias:
+      $ref: "#/components/parameters/Limit"
+    Id: { name: id, in: path, required: true, schema: { type: integer } }
+    Limit: { name: limit, in: query, schema: { type: integer } }
+  schemas:
+    Pet: { type: object }
+"##,
+        )
+        .unwrap();
+        inline_references(&mut openapi).unwrap();
+
+        let operation = openapi.paths["/pets/{id}"]
+            .to_item_ref()
+            .get
+            .as_ref()
+            .unwrap();
+        let parameters: Vec<_> = operation.parameters.iter().filter_map(key).collect();
+        assert_eq!(
+            parameters,
+            [
+                ("limit".to_owned(), "query".to_owned()),
+                ("id".to_owned(), "path".to_owned()),
+                ("limit".to_owned(), "header".to_owned())
+            ]
+        );
+        // Schemas are left for the resolver
+        let response =
+            operation.responses.responses[&openapiv3::StatusCode::Code(200)].to_item_ref();
+        assert!(matches!(
+            response.content["application/json"].schema,
+            Some(ReferenceOr::Reference { .. })
+        ));
+    }
+
+    #[test]
+    fn test_operation_parameter_overrides() {
+        let mut openapi: OpenAPI = serde_yaml::from_str(
+            r##"
+openapi: 3.0.0
+info: { title: test, version: "1" }
+paths:
+  /pets:
+    parameters:
+      - { name: limit, in: query, schema: { type: string } }
+    get:
+      parameters:
+        - { name: limit, in: query, schema: { type: integer } }
+      responses: {}
+"##,
+        )
+        .unwrap();
+        inline_references(&mut openapi).unwrap();
+
+        let operation = openapi.paths["/pets"].to_item_ref().get.as_ref().unwrap();
+        assert_eq!(operation.parameters.len(), 1);
+        match operation.parameters[0].to_item_ref() {
+            Parameter::Query { parameter_data, .. } => assert!(matches!(
+                &parameter_data.format,
+                ParameterSchemaOrContent::Schema(ReferenceOr::Item(Schema {
+                    schema_kind: SchemaKind::Type(Type::Integer(_)),
+                    ..
+                }))
+            )),
+            parameter => panic!("expected a query parameter, got {:?}", parameter),
+        }
+    }
+
+    #[test]
+    fn test_missing_reference() {
+        let resolver = Resolver::default();
+        let error = resolver.schema("#/components/schemas/Pet").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unable to resolve reference `#/components/schemas/Pet`: no such component"
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use openapiv3::{PathStyle, QueryStyle};
+use serde_json::Value;
+
+/// A parameter value split into the parts that styles put delimiters between
+enum Parts {
+    Primitive(String),
+    Array(Vec<String>),
+    Object(Vec<(String, String)>),
+}
+
+impl Parts {
+    fn new(value: &Value) -> Parts {
+        match value {
+            Value::Array(items) => Parts::Array(items.iter().map(primitive_to_string).collect()),
+            Value::Object(object) => Parts::Object(
+                object
+                    .iter()
+                    .map(|(name, value)| (name.clone(), primitive_to_string(value)))
+                    .collect(),
+            ),
+            value => Parts::Primitive(primitive_to_string(value)),
+        }
+    }
+}
+
+/// Converts a value to a string. Styles do not define how nested arrays and
+/// objects are serialized, so they are sent as JSON.
+fn primitive_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Joins the properties of an object as `name,value,name,value` or, when
+/// exploded, as `name=value,name=value`.
+fn join_object(properties: Vec<(String, String)>, explode: bool, separator: &str) -> String {
+    if explode {
+        properties
+            .into_iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join(separator)
+    } else {
+        properties
+            .into_iter()
+            .flat_map(|(name, value)| [name, value])
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Serializes a query parameter to the name-value pairs of the query string,
+/// e.g. `id=3&id=4` for an exploded array or `id[role]=admin` for `deepObject`.
+pub fn query_parameter(
+    name: &str,
+    value: &Value,
+    style: &QueryStyle,
+    explode: bool,
+) -> Vec<(String, String)> {
+    let delimiter = match style {
+        QueryStyle::SpaceDelimited => " ",
+        QueryStyle::PipeDelimited => "|",
+        QueryStyle::Form | QueryStyle::DeepObject => ",",
+    };
+    match Parts::new(value) {
+        Parts::Object(properties) if *style == QueryStyle::DeepObject => properties
+            .into_iter()
+            .map(|(property, value)| (format!("{name}[{property}]"), value))
+            .collect(),
+        Parts::Primitive(value) => vec![(name.to_owned(), value)],
+        Parts::Array(items) if explode => items
+            .into_iter()
+            .map(|item| (name.to_owned(), item))
+            .collect(),
+        Parts::Object(properties) if explode => properties,
+        Parts::Array(items) => vec![(name.to_owned(), items.join(delimiter))],
+        Parts::Object(properties) => vec![(
+            name.to_owned(),
+            properties
+                .into_iter()
+                .flat_map(|(name, value)| [name, value])
+                .collect::<Vec<_>>()
+                .join(delimiter),
+        )],
+    }
+}
+
+/// Serializes a path parameter, e.g. `3,4` with the `simple` style, `.3.4` with
+/// `label` or `;id=3;id=4` with `matrix`.
+pub fn path_parameter(name: &str, value: &Value, style: &PathStyle, explode: bool) -> String {
+    match style {
+        PathStyle::Simple => simple_parameter(value, explode),
+        PathStyle::Label => {
+            let separator = if explode { "." } else { "," };
+            match Parts::new(value) {
+                Parts::Primitive(value) => format!(".{value}"),
+                Parts::Array(items) => format!(".{}", items.join(separator)),
+                Parts::Object(properties) => {
+                    format!(".{}", join_object(properties, explode, separator))
+                }
+            }
+        }
+        PathStyle::Matrix => {
+            let pair = |name: &str, value: String| {
+                if value.is_empty() {
+                    format!(";{name}")
+                } else {
+                    format!(";{name}={value}")
+                }
+            };
+            match Parts::new(value) {
+                Parts::Primitive(value) => pair(name, value),
+                Parts::Array(items) if explode => {
+                    items.into_iter().map(|item| pair(name, item)).collect()
+                }
+                Parts::Array(items) => pair(name, items.join(",")),
+                Parts::Object(properties) if explode => properties
+                    .into_iter()
+                    .map(|(property, value)| pair(&property, value))
+                    .collect(),
+                Parts::Object(properties) => pair(name, join_object(properties, false, ",")),
+            }
+        }
+    }
+}
+
+/// Serializes a cookie parameter with the `form` style, which is the only one
+/// defined for cookies.
+pub fn cookie_parameter(name: &str, value: &Value, explode: bool) -> Vec<(String, String)> {
+    query_parameter(name, value, &QueryStyle::Form, explode)
+}
+
+/// Serializes a parameter with the `simple` style used by headers and paths,
+/// e.g. `3,4` for an array.
+pub fn simple_parameter(value: &Value, explode: bool) -> String {
+    match Parts::new(value) {
+        Parts::Primitive(value) => value,
+        Parts::Array(items) => items.join(","),
+        Parts::Object(properties) => join_object(properties, explode, ","),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_query_styles() {
+        let array = json!([3, 4, 5]);
+        let object = json!({ "firstName": "Alex", "role": "admin" });
+
+        assert_eq!(
+            query_parameter("id", &array, &QueryStyle::Form, true),
+            pairs(&[("id", "3"), ("id", "4"), ("id", "5")])
+        );
+        assert_eq!(
+            query_parameter("id", &array, &QueryStyle::Form, false),
+            pairs(&[("id", "3,4,5")])
+        );
+        assert_eq!(
+            query_parameter("id", &array, &QueryStyle::SpaceDelimited, false),
+            pairs(&[("id", "3 4 5")])
+        );
+        assert_eq!(
+            query_parameter("id", &array, &QueryStyle::PipeDelimited, false),
+            pairs(&[("id", "3|4|5")])
+        );
+        assert_eq!(
+            query_parameter("id", &object, &QueryStyle::Form, true),
+            pairs(&[("firstName", "Alex"), ("role", "admin")])
+        );
+        assert_eq!(
+            query_parameter("id", &object, &QueryStyle::DeepObject, true),
+            pairs(&[("id[firstName]", "Alex"), ("id[role]", "admin")])
+        );
+    }
+
+    #[test]
+    fn test_path_styles() {
+        let array = json!([3, 4, 5]);
+        let object = json!({ "firstName": "Alex", "role": "admin" });
+
+        assert_eq!(
+            path_parameter("id", &json!(5), &PathStyle::Simple, false),
+            "5"
+        );
+        assert_eq!(
+            path_parameter("id", &json!(5), &PathStyle::Label, false),
+            ".5"
+        );
+        assert_eq!(
+            path_parameter("id", &json!(5), &PathStyle::Matrix, false),
+            ";id=5"
+        );
+        assert_eq!(
+            path_parameter("id", &array, &PathStyle::Label, true),
+            ".3.4.5"
+        );
+        assert_eq!(
+            path_parameter("id", &array, &PathStyle::Matrix, true),
+            ";id=3;id=4;id=5"
+        );
+        assert_eq!(
+            path_parameter("id", &object, &PathStyle::Simple, false),
+            "firstName,Alex,role,admin"
+        );
+        assert_eq!(
+            path_parameter("id", &object, &PathStyle::Simple, true),
+            "firstName=Alex,role=admin"
+        );
+        assert_eq!(
+            path_parameter("id", &object, &PathStyle::Matrix, true),
+            ";firstName=Alex;role=admin"
+        );
+    }
+}
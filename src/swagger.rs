@@ -0,0 +1,649 @@
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Map, Value};
+
+const METHODS: &[&str] = &[
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Keywords of Swagger 2.0 parameters, headers and items that belong to the
+/// schema in OpenAPI 3.0
+const SCHEMA_KEYWORDS: &[&str] = &[
+    "type",
+    "format",
+    "enum",
+    "default",
+    "maximum",
+    "exclusiveMaximum",
+    "minimum",
+    "exclusiveMinimum",
+    "maxLength",
+    "minLength",
+    "pattern",
+    "maxItems",
+    "minItems",
+    "uniqueItems",
+    "multipleOf",
+];
+
+const FORM_MEDIA_TYPES: &[&str] = &["application/x-www-form-urlencoded", "multipart/form-data"];
+
+/// Converts a Swagger 2.0 document to an OpenAPI 3.0 one. Body and form data
+/// parameters become request bodies with the media types of `consumes`, the
+/// schemas of responses get the media types of `produces` and `host` and
+/// `basePath` become servers.
+pub fn convert(swagger: &Value) -> Result<Value> {
+    let converter = Converter {
+        consumes: media_types(swagger.get("consumes")),
+        produces: media_types(swagger.get("produces")),
+        parameters: object(swagger, "parameters"),
+    };
+
+    let mut schemas = Map::new();
+    for (name, schema) in object(swagger, "definitions") {
+        schemas.insert(name, convert_schema(&schema));
+    }
+    let mut parameters = Map::new();
+    for (name, parameter) in &converter.parameters {
+        if !is_payload(parameter) {
+            parameters.insert(name.clone(), convert_parameter(parameter));
+        }
+    }
+    let mut responses = Map::new();
+    for (name, response) in object(swagger, "responses") {
+        responses.insert(name, converter.response(&response, &converter.produces));
+    }
+    let mut security_schemes = Map::new();
+    for (name, scheme) in object(swagger, "securityDefinitions") {
+        let scheme = security_scheme(&name, &scheme)
+            .context(format!("Invalid security definition `{name}`"))?;
+        if let Some(scheme) = scheme {
+            security_schemes.insert(name, scheme);
+        }
+    }
+    let mut paths = Map::new();
+    for (path, item) in object(swagger, "paths") {
+        let item = converter
+            .path_item(&item)
+            .context(format!("Invalid path `{path}`"))?;
+        paths.insert(path, item);
+    }
+
+    let mut openapi = json!({
+        "openapi": "3.0.3",
+        "info": swagger.get("info").cloned().unwrap_or_else(|| json!({})),
+        "servers": servers(swagger),
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "parameters": parameters,
+            "responses": responses,
+            "securitySchemes": security_schemes,
+        },
+    });
+    for key in &["tags", "externalDocs", "security"] {
+        if let Some(value) = swagger.get(*key) {
+            openapi[*key] = value.clone();
+        }
+    }
+    rewrite_references(&mut openapi);
+    Ok(openapi)
+}
+
+fn object(value: &Value, key: &str) -> Map<String, Value> {
+    match value.get(key) {
+        Some(Value::Object(object)) => object.clone(),
+        _ => Map::new(),
+    }
+}
+
+fn media_types(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(str::to_owned)
+        .collect()
+}
+
+fn servers(swagger: &Value) -> Value {
+    let base_path = swagger
+        .get("basePath")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    match swagger.get("host").and_then(Value::as_str) {
+        Some(host) => {
+            let mut schemes = media_types(swagger.get("schemes"));
+            if schemes.is_empty() {
+                schemes.push("https".to_owned());
+            }
+            schemes
+                .iter()
+                .map(|scheme| json!({ "url": format!("{scheme}://{host}{base_path}") }))
+                .collect()
+        }
+        None if base_path.is_empty() => json!([]),
+        None => json!([{ "url": base_path }]),
+    }
+}
+
+/// Points references to definitions, parameters and responses to components.
+fn rewrite_references(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                match value {
+                    Value::String(reference) if key == "$ref" => {
+                        for (from, to) in &[
+                            ("#/definitions/", "#/components/schemas/"),
+                            ("#/parameters/", "#/components/parameters/"),
+                            ("#/responses/", "#/components/responses/"),
+                        ] {
+                            if let Some(name) = reference.strip_prefix(from) {
+                                *reference = format!("{to}{name}");
+                                break;
+                            }
+                        }
+                    }
+                    value => rewrite_references(value),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(rewrite_references),
+        _ => {}
+    }
+}
+
+/// Whether a parameter is sent in the request body
+fn is_payload(parameter: &Value) -> bool {
+    matches!(
+        parameter.get("in").and_then(Value::as_str),
+        Some("body" | "formData")
+    )
+}
+
+fn convert_schema(schema: &Value) -> Value {
+    let mut schema = schema.clone();
+    let object = match schema.as_object_mut() {
+        Some(object) if !object.contains_key("$ref") => object,
+        _ => return schema,
+    };
+    if object.get("type").and_then(Value::as_str) == Some("file") {
+        object.insert("type".to_owned(), "string".into());
+        object.insert("format".to_owned(), "binary".into());
+    }
+    if let Some(nullable) = object.remove("x-nullable") {
+        object.insert("nullable".to_owned(), nullable);
+    }
+    if let Some(Value::String(property)) = object.get("discriminator") {
+        let discriminator = json!({ "propertyName": property });
+        object.insert("discriminator".to_owned(), discriminator);
+    }
+    for key in &["items", "additionalProperties"] {
+        if let Some(subschema) = object.get_mut(*key) {
+            *subschema = convert_schema(subschema);
+        }
+    }
+    if let Some(Value::Object(properties)) = object.get_mut("properties") {
+        for property in properties.values_mut() {
+            *property = convert_schema(property);
+        }
+    }
+    if let Some(Value::Array(schemas)) = object.get_mut("allOf") {
+        for subschema in schemas {
+            *subschema = convert_schema(subschema);
+        }
+    }
+    schema
+}
+
+/// Moves the schema keywords of a parameter, header or items object to a schema.
+fn to_schema(object: &Value) -> Value {
+    let mut schema = Map::new();
+    for key in SCHEMA_KEYWORDS {
+        if let Some(value) = object.get(*key) {
+            schema.insert(key.to_string(), value.clone());
+        }
+    }
+    if let Some(items) = object.get("items") {
+        schema.insert("items".to_owned(), to_schema(items));
+    }
+    convert_schema(&Value::Object(schema))
+}
+
+/// Converts a security definition to a security scheme, or returns `None` if
+/// OpenAPI 3 has no such scheme.
+fn security_scheme(name: &str, definition: &Value) -> Result<Option<Value>> {
+    let mut scheme = match definition.get("type").and_then(Value::as_str) {
+        Some("basic") => json!({ "type": "http", "scheme": "basic" }),
+        Some("apiKey") => {
+            let mut scheme = json!({ "type": "apiKey" });
+            for key in &["name", "in"] {
+                match definition.get(*key) {
+                    Some(value) => scheme[*key] = value.clone(),
+                    None => bail!("Missing `{}` of API key", key),
+                }
+            }
+            scheme
+        }
+        Some("oauth2") => {
+            let flow = match definition.get("flow").and_then(Value::as_str) {
+                Some("implicit") => "implicit",
+                Some("password") => "password",
+                Some("application") => "clientCredentials",
+                Some("accessCode") => "authorizationCode",
+                flow => {
+                    eprintln!(
+                        "Security definition `{name}`: unknown oauth2 flow {flow:?}, skipping it"
+                    );
+                    return Ok(None);
+                }
+            };
+            let mut converted = Map::new();
+            for key in &["authorizationUrl", "tokenUrl", "scopes"] {
+                if let Some(value) = definition.get(*key) {
+                    converted.insert(key.to_string(), value.clone());
+                }
+            }
+            json!({ "type": "oauth2", "flows": { flow: converted } })
+        }
+        kind => {
+            eprintln!("Security definition `{name}`: unknown type {kind:?}, skipping it");
+            return Ok(None);
+        }
+    };
+    if let Some(description) = definition.get("description") {
+        scheme["description"] = description.clone();
+    }
+    Ok(Some(scheme))
+}
+
+/// Converts a parameter sent in the path, query or headers.
+fn convert_parameter(parameter: &Value) -> Value {
+    if parameter.get("$ref").is_some() {
+        return parameter.clone();
+    }
+    let mut converted = Map::new();
+    for key in &["name", "in", "description", "required", "allowEmptyValue"] {
+        if let Some(value) = parameter.get(*key) {
+            converted.insert(key.to_string(), value.clone());
+        }
+    }
+    let mut schema = to_schema(parameter);
+    if let Some(example) = parameter.get("x-example") {
+        schema["example"] = example.clone();
+    }
+    converted.insert("schema".to_owned(), schema);
+
+    // Arrays are comma separated by default
+    if parameter.get("type").and_then(Value::as_str) == Some("array") {
+        let in_query = parameter.get("in").and_then(Value::as_str) == Some("query");
+        let collection_format = parameter.get("collectionFormat").and_then(Value::as_str);
+        if collection_format == Some("tsv") {
+            eprintln!(
+                "Parameter `{}`: tab separated values are not supported by OpenAPI 3, sending them comma separated",
+                parameter
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+            );
+        }
+        let (style, explode) = match collection_format {
+            Some("multi") => ("form", true),
+            Some("ssv") if in_query => ("spaceDelimited", false),
+            Some("pipes") if in_query => ("pipeDelimited", false),
+            _ if in_query => ("form", false),
+            _ => ("simple", false),
+        };
+        converted.insert("style".to_owned(), style.into());
+        converted.insert("explode".to_owned(), explode.into());
+    }
+    Value::Object(converted)
+}
+
+struct Converter {
+    /// Media types of request bodies used by operations without their own
+    consumes: Vec<String>,
+    /// Media types of responses used by operations without their own
+    produces: Vec<String>,
+    /// Parameters of the document, which body and form data parameters are
+    /// inlined from
+    parameters: Map<String, Value>,
+}
+
+impl Converter {
+    /// Looks up the parameter a reference points to if it is sent in the body,
+    /// as those are not kept as parameters.
+    fn resolve(&self, parameter: &Value) -> Result<Value> {
+        let reference = match parameter.get("$ref").and_then(Value::as_str) {
+            Some(reference) => reference,
+            None => return Ok(parameter.clone()),
+        };
+        let referenced = reference
+            .strip_prefix("#/parameters/")
+            .and_then(|name| self.parameters.get(name));
+        match referenced {
+            Some(referenced) if is_payload(referenced) => Ok(referenced.clone()),
+            Some(_) => Ok(parameter.clone()),
+            None => bail!("Unable to resolve reference `{}`", reference),
+        }
+    }
+
+    fn path_item(&self, item: &Value) -> Result<Value> {
+        let mut converted = Map::new();
+        let mut payload = vec![];
+        let mut parameters = vec![];
+        for parameter in item
+            .get("parameters")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let parameter = self.resolve(parameter)?;
+            if is_payload(&parameter) {
+                payload.push(parameter);
+            } else {
+                parameters.push(convert_parameter(&parameter));
+            }
+        }
+        if !parameters.is_empty() {
+            converted.insert("parameters".to_owned(), parameters.into());
+        }
+        for method in METHODS {
+            if let Some(operation) = item.get(*method) {
+                let operation = self
+                    .operation(operation, &payload)
+                    .context(format!("Invalid operation `{method}`"))?;
+                converted.insert(method.to_string(), operation);
+            }
+        }
+        Ok(Value::Object(converted))
+    }
+
+    /// Converts an operation, with the body and form data parameters of its path
+    /// item in `shared` unless it overrides them.
+    fn operation(&self, operation: &Value, shared: &[Value]) -> Result<Value> {
+        let mut converted = operation.as_object().cloned().unwrap_or_default();
+        for key in &["parameters", "consumes", "produces", "schemes", "responses"] {
+            converted.remove(*key);
+        }
+
+        let mut own = vec![];
+        for parameter in operation
+            .get("parameters")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            own.push(self.resolve(parameter)?);
+        }
+        let overridden = |shared: &&Value| {
+            own.iter()
+                .any(|p| p.get("name") == shared.get("name") && p.get("in") == shared.get("in"))
+        };
+        let shared: Vec<_> = shared.iter().filter(|p| !overridden(p)).cloned().collect();
+
+        let mut body = None;
+        let mut form = vec![];
+        let mut parameters = vec![];
+        for parameter in own.into_iter().chain(shared) {
+            match parameter.get("in").and_then(Value::as_str) {
+                // An operation has a single body and its own one comes first
+                Some("body") if body.is_none() => body = Some(parameter),
+                Some("body") => {}
+                Some("formData") => form.push(parameter),
+                _ => parameters.push(convert_parameter(&parameter)),
+            }
+        }
+        if !parameters.is_empty() {
+            converted.insert("parameters".to_owned(), parameters.into());
+        }
+
+        let consumes = match media_types(operation.get("consumes")) {
+            consumes if consumes.is_empty() => self.consumes.clone(),
+            consumes => consumes,
+        };
+        if let Some(body) = body {
+            converted.insert("requestBody".to_owned(), request_body(&body, &consumes)?);
+        } else if !form.is_empty() {
+            converted.insert("requestBody".to_owned(), form_body(&form, &consumes));
+        }
+
+        let produces = match media_types(operation.get("produces")) {
+            produces if produces.is_empty() => self.produces.clone(),
+            produces => produces,
+        };
+        let mut responses = Map::new();
+        for (status, response) in object(operation, "responses") {
+            responses.insert(status, self.response(&response, &produces));
+        }
+        converted.insert("responses".to_owned(), responses.into());
+        Ok(Value::Object(converted))
+    }
+
+    fn response(&self, response: &Value, produces: &[String]) -> Value {
+        if response.get("$ref").is_some() {
+            return response.clone();
+        }
+        let mut converted = Map::new();
+        let description = response.get("description").cloned();
+        converted.insert(
+            "description".to_owned(),
+            description.unwrap_or_else(|| "".into()),
+        );
+        let mut headers = Map::new();
+        for (name, header) in object(response, "headers") {
+            let mut converted_header = json!({ "schema": to_schema(&header) });
+            if let Some(description) = header.get("description") {
+                converted_header["description"] = description.clone();
+            }
+            headers.insert(name, converted_header);
+        }
+        if !headers.is_empty() {
+            converted.insert("headers".to_owned(), headers.into());
+        }
+        if let Some(schema) = response.get("schema") {
+            let examples = object(response, "examples");
+            let mut content = Map::new();
+            for media_type in or_json(produces) {
+                let mut media_type_object = json!({ "schema": convert_schema(schema) });
+                if let Some(example) = examples.get(&media_type) {
+                    media_type_object["example"] = example.clone();
+                }
+                content.insert(media_type, media_type_object);
+            }
+            converted.insert("content".to_owned(), content.into());
+        }
+        Value::Object(converted)
+    }
+}
+
+fn or_json(media_types: &[String]) -> Vec<String> {
+    if media_types.is_empty() {
+        vec!["application/json".to_owned()]
+    } else {
+        media_types.to_vec()
+    }
+}
+
+fn request_body(body: &Value, consumes: &[String]) -> Result<Value> {
+    let schema = match body.get("schema") {
+        Some(schema) => convert_schema(schema),
+        None => bail!("Body parameter has no schema"),
+    };
+    let mut content = Map::new();
+    for media_type in or_json(consumes) {
+        content.insert(media_type, json!({ "schema": schema }));
+    }
+    let mut request_body = json!({ "content": content });
+    for key in &["description", "required"] {
+        if let Some(value) = body.get(*key) {
+            request_body[*key] = value.clone();
+        }
+    }
+    Ok(request_body)
+}
+
+/// Collects form data parameters into an object schema sent with the form media
+/// types of `consumes`, or as multipart if a file is uploaded.
+fn form_body(form: &[Value], consumes: &[String]) -> Value {
+    let mut properties = Map::new();
+    let mut required = vec![];
+    for parameter in form {
+        let name = parameter
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let mut schema = to_schema(parameter);
+        if let Some(description) = parameter.get("description") {
+            schema["description"] = description.clone();
+        }
+        properties.insert(name.to_owned(), schema);
+        if parameter.get("required") == Some(&Value::Bool(true)) {
+            required.push(Value::from(name));
+        }
+    }
+    let schema = json!({ "type": "object", "properties": properties, "required": required });
+
+    let mut media_types: Vec<_> = consumes
+        .iter()
+        .filter(|media_type| FORM_MEDIA_TYPES.contains(&media_type.as_str()))
+        .cloned()
+        .collect();
+    if media_types.is_empty() {
+        let has_file = form
+            .iter()
+            .any(|parameter| parameter.get("type").and_then(Value::as_str) == Some("file"));
+        media_types.push(FORM_MEDIA_TYPES[usize::from(has_file)].to_owned());
+    }
+    let content: Map<_, _> = media_types
+        .into_iter()
+        .map(|media_type| (media_type, json!({ "schema": schema })))
+        .collect();
+    json!({ "content": content, "required": !required.is_empty() })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use openapi_utils::ReferenceOrExt;
+    use openapiv3::{OpenAPI, QueryStyle, ReferenceOr};
+
+    #[test]
+    fn test_convert() {
+        let swagger: Value = serde_yaml::from_str(
+            r##"
+swagger: "2.0"
+info: { title: test, version: "1" }
+host: api.example.com
+basePath: /v1
+schemes: [http]
+consumes: [application/json]
+produces: [application/json]
+paths:
+  /pets/{id}:
+    parameters:
+      - { name: id, in: path, required: true, type: integer }
+    put:
+      parameters:
+        - $ref: "#/parameters/Pet"
+        - { name: tags, in: query, type: array, items: { type: string }, collectionFormat: multi }
+      responses:
+        200:
+          description: the pet
+          schema: { $ref: "#/definitions/Pet" }
+    post:
+      consumes: [multipart/form-data]
+      parameters:
+        - { name: photo, in: formData, type: file, required: true }
+        - { name: note, in: formData, type: string }
+      responses:
+        default: { description: error }
+parameters:
+  Pet: { name: pet, in: body, required: true, schema: { $ref: "#/definitions/Pet" } }
+securityDefinitions:
+  key: { type: apiKey, name: X-Key, in: header }
+  oauth: { type: oauth2, flow: accessCode, authorizationUrl: "https://a", tokenUrl: "https://t", scopes: {} }
+definitions:
+  Pet:
+    type: object
+    discriminator: kind
+    properties:
+      kind: { type: string }
+      owner: { type: string, x-nullable: true }
+"##,
+        )
+        .unwrap();
+        let openapi: OpenAPI = serde_json::from_value(convert(&swagger).unwrap()).unwrap();
+
+        assert_eq!(openapi.servers[0].url, "http://api.example.com/v1");
+        let item = openapi.paths["/pets/{id}"].to_item_ref();
+        assert_eq!(item.parameters.len(), 1);
+
+        let put = item.put.as_ref().unwrap();
+        let body = put.request_body.as_ref().unwrap().to_item_ref();
+        assert!(body.required);
+        assert_eq!(
+            body.content["application/json"].schema,
+            Some(ReferenceOr::ref_("#/components/schemas/Pet"))
+        );
+        match put.parameters[0].to_item_ref() {
+            openapiv3::Parameter::Query {
+                style,
+                parameter_data,
+                ..
+            } => {
+                assert_eq!(parameter_data.name, "tags");
+                assert!(matches!(style, QueryStyle::Form));
+                assert_eq!(parameter_data.explode, Some(true));
+            }
+            parameter => panic!("expected a query parameter, got {:?}", parameter),
+        }
+        let response = put.responses.responses[&openapiv3::StatusCode::Code(200)].to_item_ref();
+        assert!(response.content.contains_key("application/json"));
+
+        let post = item.post.as_ref().unwrap();
+        let form = post.request_body.as_ref().unwrap().to_item_ref();
+        let schema = form.content["multipart/form-data"].schema.as_ref().unwrap();
+        let schema = serde_json::to_value(schema).unwrap();
+        assert_eq!(schema["properties"]["photo"]["format"], "binary");
+        assert_eq!(schema["required"], json!(["photo"]));
+
+        let components = openapi.components.unwrap();
+        let pet = serde_json::to_value(&components.schemas["Pet"]).unwrap();
+        assert_eq!(pet["discriminator"]["propertyName"], "kind");
+        assert_eq!(pet["properties"]["owner"]["nullable"], true);
+        let schemes = serde_json::to_value(&components.security_schemes).unwrap();
+        assert_eq!(
+            schemes["key"],
+            json!({ "type": "apiKey", "name": "X-Key", "in": "header" })
+        );
+        assert_eq!(
+            schemes["oauth"]["flows"]["authorizationCode"]["tokenUrl"],
+            "https://t"
+        );
+
+        let mut swagger = swagger;
+        swagger["paths"]["/pets/{id}"]["put"]["parameters"][1]["collectionFormat"] = "tsv".into();
+        swagger["securityDefinitions"]["oauth"]["flow"] = "device".into();
+        let openapi = convert(&swagger).unwrap();
+        let tags = &openapi["paths"]["/pets/{id}"]["put"]["parameters"][0];
+        assert_eq!(
+            (&tags["style"], &tags["explode"]),
+            (&json!("form"), &json!(false))
+        );
+        assert!(openapi["components"]["securitySchemes"]
+            .get("oauth")
+            .is_none());
+
+        swagger["securityDefinitions"]["key"]
+            .as_object_mut()
+            .unwrap()
+            .remove("name");
+        let error = convert(&swagger).unwrap_err();
+        assert_eq!(
+            format!("{error:#}"),
+            "Invalid security definition `key`: Missing `name` of API key"
+        );
+    }
+}